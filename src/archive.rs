@@ -0,0 +1,37 @@
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+
+/// One fetched page with what is known about the fetch, as produced by a
+/// `DisruptionSource` and archived by the store.
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pub fetched_at: DateTime<Utc>,
+    pub url: String,
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub duration_ms: u64,
+    pub body: String,
+}
+
+impl Snapshot {
+    /// Hex SHA-256 of the body, the key pages are deduplicated by.
+    pub fn hash(&self) -> String {
+        hex::encode(Sha256::digest(self.body.as_bytes()))
+    }
+}
+
+pub fn compress(body: &str) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(body.as_bytes())?;
+    encoder.finish()
+}
+
+pub fn decompress(compressed: &[u8]) -> std::io::Result<String> {
+    let mut body = String::new();
+    GzDecoder::new(compressed).read_to_string(&mut body)?;
+    Ok(body)
+}
@@ -0,0 +1,88 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::lifecycle::Disruption;
+use crate::Status;
+
+const DEFAULT_LIMIT: u32 = 500;
+const MAX_LIMIT: u32 = 5000;
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+impl ChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Modified => "modified",
+        }
+    }
+}
+
+impl std::str::FromStr for ChangeKind {
+    type Err = String;
+
+    fn from_str(kind: &str) -> Result<ChangeKind, String> {
+        match kind {
+            "added" => Ok(ChangeKind::Added),
+            "removed" => Ok(ChangeKind::Removed),
+            "modified" => Ok(ChangeKind::Modified),
+            _ => Err(format!("unknown change kind {:?}", kind)),
+        }
+    }
+}
+
+/// One entry of the change feed. `cursor` increases with every change and is
+/// never reused, so it can be handed back as `/changes?since=<cursor>`.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Change {
+    pub cursor: i64,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub at: DateTime<Utc>,
+    pub kind: ChangeKind,
+    pub key: String,
+    /// The new state, or the last known state for removed disruptions.
+    pub disruption: Disruption,
+    /// The state before a modification.
+    pub previous: Option<Disruption>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ChangesQuery {
+    pub since: Option<i64>,
+    pub limit: Option<u32>,
+}
+
+impl ChangesQuery {
+    pub fn since(&self) -> i64 {
+        self.since.unwrap_or(0)
+    }
+
+    pub fn limit(&self) -> u32 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct ChangeFeed {
+    /// Pass this as `since` on the next request.
+    pub cursor: i64,
+    /// More changes are available after `cursor`.
+    pub has_more: bool,
+    pub changes: Vec<Change>,
+}
+
+/// Published on `AppState.updates` whenever a scrape changed something.
+#[derive(Clone, Debug)]
+pub struct StatusUpdate {
+    /// Cursor of the last change in `changes`.
+    pub cursor: i64,
+    pub status: Status,
+    pub changes: Vec<Change>,
+}
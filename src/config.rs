@@ -0,0 +1,238 @@
+use clap::Parser;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use crate::logging::{self, LogFormat};
+use crate::source;
+use crate::webhooks::{self, Webhook};
+
+const DEFAULT_CONFIG_PATH: &str = "schwebebahndisruption.toml";
+/// Upper bound for the `_minutes` settings: a week, far beyond any sensible
+/// value and well within what `chrono::Duration::minutes` accepts.
+const MAX_MINUTES: u64 = 7 * 24 * 60;
+
+/// Settings read from the TOML config file. Every field has a default, and
+/// the scalar ones can be overridden by environment variables and flags.
+#[derive(Clone, Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub source_url: String,
+    pub bind: String,
+    pub database_path: String,
+    pub scrape_interval_minutes: u64,
+    /// Scraping pauses when no API request arrived for this long.
+    pub activity_window_minutes: u64,
+    /// Requests finding data older than this trigger a refresh.
+    pub max_age_minutes: u64,
+    /// Upper bound for `/status?wait=`.
+    pub max_wait_seconds: u64,
+    /// Data older than this is reported as stale and fails `/health`.
+    pub stale_after_minutes: u64,
+    pub log_format: LogFormat,
+    /// `tracing_subscriber::EnvFilter` directives, e.g. `info,schwebebahndisruption=debug`.
+    pub log_filter: String,
+    /// Scraping rules file, reloaded when it changes; the built-in rules
+    /// are used if unset.
+    pub rules_path: Option<PathBuf>,
+    /// HTML file, directory of HTML files or database with archived pages
+    /// that is scraped instead of `source_url`, see `source::from_config`.
+    pub replay: Option<PathBuf>,
+    /// Parse every page archived in `replay` at its fetch time, then exit
+    /// instead of serving; see `refresh::reparse`. Only set by `--reparse`.
+    #[serde(skip)]
+    pub reparse: bool,
+    pub webhooks: Vec<Webhook>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            source_url: "https://www.wsw-online.de/mobilitaet/fahrplan/fahrtauskunft/verkehrsinformationen/".to_string(),
+            bind: "0.0.0.0:8070".to_string(),
+            database_path: "schwebebahndisruption.db".to_string(),
+            scrape_interval_minutes: 15,
+            activity_window_minutes: 20,
+            max_age_minutes: 5,
+            max_wait_seconds: 10,
+            stale_after_minutes: 30,
+            log_format: LogFormat::Text,
+            log_filter: "info".to_string(),
+            rules_path: None,
+            replay: None,
+            reparse: false,
+            webhooks: Vec::new(),
+        }
+    }
+}
+
+/// Command line flags. Each one can also be set through the environment
+/// variable next to it; flags win over the environment, which wins over the
+/// config file.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    /// TOML config file; `schwebebahndisruption.toml` is used if it exists.
+    #[arg(long, short, env = "SCHWEBEBAHN_CONFIG")]
+    pub config: Option<PathBuf>,
+    #[arg(long, env = "SCHWEBEBAHN_SOURCE_URL")]
+    pub source_url: Option<String>,
+    #[arg(long, env = "SCHWEBEBAHN_BIND")]
+    pub bind: Option<String>,
+    #[arg(long, env = "SCHWEBEBAHN_DATABASE_PATH")]
+    pub database_path: Option<String>,
+    #[arg(long, env = "SCHWEBEBAHN_SCRAPE_INTERVAL_MINUTES")]
+    pub scrape_interval_minutes: Option<u64>,
+    #[arg(long, env = "SCHWEBEBAHN_ACTIVITY_WINDOW_MINUTES")]
+    pub activity_window_minutes: Option<u64>,
+    #[arg(long, env = "SCHWEBEBAHN_MAX_AGE_MINUTES")]
+    pub max_age_minutes: Option<u64>,
+    #[arg(long, env = "SCHWEBEBAHN_MAX_WAIT_SECONDS")]
+    pub max_wait_seconds: Option<u64>,
+    #[arg(long, env = "SCHWEBEBAHN_STALE_AFTER_MINUTES")]
+    pub stale_after_minutes: Option<u64>,
+    #[arg(long, env = "SCHWEBEBAHN_LOG_FORMAT")]
+    pub log_format: Option<LogFormat>,
+    #[arg(long, env = "SCHWEBEBAHN_LOG_FILTER")]
+    pub log_filter: Option<String>,
+    #[arg(long, env = "SCHWEBEBAHN_RULES_PATH")]
+    pub rules_path: Option<PathBuf>,
+    #[arg(long, env = "SCHWEBEBAHN_REPLAY")]
+    pub replay: Option<PathBuf>,
+    /// Re-parse all pages archived in `--replay` into `--database-path`,
+    /// each at the time it was fetched, then exit.
+    #[arg(long)]
+    pub reparse: bool,
+}
+
+impl Config {
+    /// Reads the config file named by `cli` (or the default one, if present),
+    /// applies the overrides and validates the result.
+    pub fn load(cli: Cli) -> Result<Config, String> {
+        let mut config = match &cli.config {
+            Some(path) => read(path)?,
+            None if std::path::Path::new(DEFAULT_CONFIG_PATH).exists() => read(&PathBuf::from(DEFAULT_CONFIG_PATH))?,
+            None => Config::default(),
+        };
+
+        if let Some(source_url) = cli.source_url {
+            config.source_url = source_url;
+        }
+        if let Some(bind) = cli.bind {
+            config.bind = bind;
+        }
+        if let Some(database_path) = cli.database_path {
+            config.database_path = database_path;
+        }
+        if let Some(scrape_interval_minutes) = cli.scrape_interval_minutes {
+            config.scrape_interval_minutes = scrape_interval_minutes;
+        }
+        if let Some(activity_window_minutes) = cli.activity_window_minutes {
+            config.activity_window_minutes = activity_window_minutes;
+        }
+        if let Some(max_age_minutes) = cli.max_age_minutes {
+            config.max_age_minutes = max_age_minutes;
+        }
+        if let Some(max_wait_seconds) = cli.max_wait_seconds {
+            config.max_wait_seconds = max_wait_seconds;
+        }
+        if let Some(stale_after_minutes) = cli.stale_after_minutes {
+            config.stale_after_minutes = stale_after_minutes;
+        }
+        if let Some(log_format) = cli.log_format {
+            config.log_format = log_format;
+        }
+        if let Some(log_filter) = cli.log_filter {
+            config.log_filter = log_filter;
+        }
+        if let Some(rules_path) = cli.rules_path {
+            config.rules_path = Some(rules_path);
+        }
+        if let Some(replay) = cli.replay {
+            config.replay = Some(replay);
+        }
+        config.reparse = cli.reparse;
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Collects every problem instead of stopping at the first, so a broken
+    /// deployment can be fixed in one go.
+    fn validate(&self) -> Result<(), String> {
+        let mut errors = Vec::new();
+
+        if let Err(e) = validate_url(&self.source_url) {
+            errors.push(format!("source_url: {}", e));
+        }
+        if self.bind.parse::<SocketAddr>().is_err() {
+            errors.push(format!("bind: {:?} is not an address like 0.0.0.0:8070", self.bind));
+        }
+        if self.database_path.trim().is_empty() {
+            errors.push("database_path: must not be empty".to_string());
+        }
+        for (name, minutes) in [
+            ("scrape_interval_minutes", self.scrape_interval_minutes),
+            ("activity_window_minutes", self.activity_window_minutes),
+            ("max_age_minutes", self.max_age_minutes),
+        ] {
+            if !(1..=MAX_MINUTES).contains(&minutes) {
+                errors.push(format!("{}: must be between 1 and {}", name, MAX_MINUTES));
+            }
+        }
+        if self.max_wait_seconds > 60 {
+            errors.push("max_wait_seconds: must be at most 60".to_string());
+        }
+        if self.stale_after_minutes < self.max_age_minutes {
+            errors.push("stale_after_minutes: must not be less than max_age_minutes".to_string());
+        } else if self.stale_after_minutes > MAX_MINUTES {
+            errors.push(format!("stale_after_minutes: must be at most {}", MAX_MINUTES));
+        }
+        if let Some(replay) = &self.replay {
+            if !replay.exists() {
+                errors.push(format!("replay: {} does not exist", replay.display()));
+            } else if replay.is_file() && replay == std::path::Path::new(&self.database_path) {
+                errors.push("replay: must not be the database_path the replay is recorded in".to_string());
+            }
+        }
+        if self.reparse && !self.replay.as_deref().is_some_and(source::is_archive) {
+            errors.push("reparse: needs replay to be a database with archived pages".to_string());
+        }
+        if let Err(e) = logging::validate_filter(&self.log_filter) {
+            errors.push(format!("log_filter: {}", e));
+        }
+        for (index, webhook) in self.webhooks.iter().enumerate() {
+            if let Err(e) = validate_url(&webhook.url) {
+                errors.push(format!("webhooks[{}].url: {}", index, e));
+            }
+            if webhook.secret.is_empty() {
+                errors.push(format!("webhooks[{}].secret: must not be empty", index));
+            }
+            if !(1..=webhooks::MAX_ATTEMPTS).contains(&webhook.max_attempts) {
+                errors.push(format!("webhooks[{}].max_attempts: must be between 1 and {}", index, webhooks::MAX_ATTEMPTS));
+            }
+            if let Some(invalid) = webhook.transportation.iter().find(|t| *t != "subway" && *t != "elevator") {
+                errors.push(format!("webhooks[{}].transportation: unknown value {:?}", index, invalid));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+}
+
+fn read(path: &PathBuf) -> Result<Config, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn validate_url(url: &str) -> Result<(), String> {
+    match reqwest::Url::parse(url) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => Ok(()),
+        Ok(url) => Err(format!("unsupported scheme {:?}", url.scheme())),
+        Err(e) => Err(format!("{:?} is not a valid URL: {}", url, e)),
+    }
+}
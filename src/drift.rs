@@ -0,0 +1,56 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashSet;
+use tracing::error;
+
+use crate::scrape::{Scrape, ScrapeError};
+use crate::store::Store;
+
+/// Scrapes recorded within this window are what an empty page is compared to.
+/// A page that stays empty for longer is eventually taken at its word.
+const HISTORY_HOURS: i64 = 24;
+/// An empty page is only suspicious if every scrape in the window had at
+/// least this many rows; a quiet day may well end with everything resolved.
+const MIN_HISTORY_ROWS: u64 = 3;
+
+/// Sanity checks of a parsed page, so a redesign that makes the selectors
+/// miss is reported as `ScrapeError::StructureChanged` instead of being
+/// served as "no disruptions". The table container itself is checked by
+/// `scrape::parse_page`.
+pub fn check(scrape: &Scrape, store: &Store, now: DateTime<Utc>) -> Result<(), ScrapeError> {
+    // Rows for other modes, such as buses, are ignored on purpose; only rows
+    // that lost the attribute point at a redesign.
+    let parsed = scrape.schwebebahn.len() + scrape.elevators.len();
+    if scrape.unlabelled > 0 && parsed == 0 {
+        return Err(ScrapeError::StructureChanged(format!("{} of {} rows have no data-transportation", scrape.unlabelled, scrape.rows)));
+    }
+
+    // Only cells the parser can't do without; rows without an id or
+    // location are common on the real page.
+    let incomplete: HashSet<usize> = scrape
+        .warnings
+        .iter()
+        .filter_map(|warning| match warning {
+            ScrapeError::InvalidRow { index, required: true, .. } => Some(*index),
+            _ => None,
+        })
+        .collect();
+    if parsed > 0 && incomplete.len() * 2 > parsed {
+        return Err(ScrapeError::StructureChanged(format!("{} of {} rows are missing required cells", incomplete.len(), parsed)));
+    }
+
+    if scrape.rows == 0 {
+        let counts = store.row_counts_since(now - Duration::hours(HISTORY_HOURS)).unwrap_or_else(|e| {
+            error!(error = %e, "Error loading row count history");
+            Vec::new()
+        });
+        if let Some(min) = counts.iter().min().filter(|&&min| min >= MIN_HISTORY_ROWS) {
+            return Err(ScrapeError::StructureChanged(format!(
+                "no rows, while each of the last {} scrapes had at least {}",
+                counts.len(),
+                min
+            )));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::scrape::ScrapeError;
+use crate::{current_status, refresh, AppState};
+
+/// How the scraping has been going, served as part of `Status`.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct ScrapeHealth {
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    pub last_attempt: Option<DateTime<Utc>>,
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    pub last_success: Option<DateTime<Utc>>,
+    /// Error of the most recent attempt, cleared by the next success.
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+    /// Set once the page no longer looks like the one the parser was written
+    /// for. The data is then from before that and the disruptions are unknown.
+    #[serde(default)]
+    pub parser_broken: bool,
+    /// Rows on the page in the last successful scrape, the history drift
+    /// detection compares an empty page to.
+    #[serde(default)]
+    pub rows: usize,
+    /// Rows of the last successful scrape that could only be read in part.
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Filled in when serving: the data is older than `stale_after_minutes`.
+    pub stale: bool,
+}
+
+impl ScrapeHealth {
+    pub fn record_success(&mut self, at: DateTime<Utc>, rows: usize, warnings: Vec<String>) {
+        self.last_attempt = Some(at);
+        self.last_success = Some(at);
+        self.last_error = None;
+        self.consecutive_failures = 0;
+        self.parser_broken = false;
+        self.rows = rows;
+        self.warnings = warnings;
+    }
+
+    /// Network and HTTP errors leave `parser_broken` as it was, they say
+    /// nothing about the page.
+    pub fn record_failure(&mut self, at: DateTime<Utc>, error: &ScrapeError) {
+        self.last_attempt = Some(at);
+        self.last_error = Some(error.to_string());
+        self.consecutive_failures += 1;
+        if let ScrapeError::StructureChanged(_) = error {
+            self.parser_broken = true;
+        }
+    }
+}
+
+/// Data that was never scraped or not refreshed within `stale_after_minutes`.
+pub fn is_stale(last_updated: Option<DateTime<Utc>>, stale_after_minutes: u64) -> bool {
+    match last_updated {
+        Some(time) => Utc::now() - time > Duration::minutes(stale_after_minutes as i64),
+        None => true,
+    }
+}
+
+/// `/health`: 200 while the data is fresh, 503 once it is stale or the parser
+/// is broken, with the `ScrapeHealth` as body either way. Stale data also triggers a refresh, so
+/// a monitor polling this keeps the data current like a client would.
+pub async fn health(data: web::Data<Arc<AppState>>) -> HttpResponse {
+    if refresh::is_stale(&data) {
+        let state = Arc::clone(&data);
+        tokio::spawn(async move { refresh::refresh(&state).await });
+    }
+
+    let health = current_status(&data).health;
+    if health.stale || health.parser_broken {
+        HttpResponse::ServiceUnavailable().json(health)
+    } else {
+        HttpResponse::Ok().json(health)
+    }
+}
@@ -0,0 +1,59 @@
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::Status;
+
+const DEFAULT_PER_PAGE: u32 = 50;
+const MAX_PER_PAGE: u32 = 500;
+
+/// Query string of the `/history` endpoints. Times are unix seconds.
+#[derive(Deserialize, Debug, Default)]
+pub struct HistoryQuery {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    /// A station id, name or alias, matched as in `Disruption::mentions_station`.
+    pub station: Option<String>,
+    /// `subway` or `elevator`, as in `data-transportation`.
+    pub transportation: Option<String>,
+    pub event: Option<String>,
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+impl HistoryQuery {
+    pub fn from(&self) -> Option<DateTime<Utc>> {
+        self.from.and_then(|from| Utc.timestamp_opt(from, 0).single())
+    }
+
+    pub fn to(&self) -> Option<DateTime<Utc>> {
+        self.to.and_then(|to| Utc.timestamp_opt(to, 0).single())
+    }
+
+    /// 1-based page number.
+    pub fn page(&self) -> u32 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    pub fn per_page(&self) -> u32 {
+        self.per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE)
+    }
+
+    pub fn offset(&self) -> u32 {
+        (self.page() - 1).saturating_mul(self.per_page())
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct Page<T> {
+    pub page: u32,
+    pub per_page: u32,
+    pub total: u64,
+    pub items: Vec<T>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ScrapeRecord {
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub scraped_at: DateTime<Utc>,
+    pub status: Status,
+}
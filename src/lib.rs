@@ -0,0 +1,440 @@
+use actix_web::{web, App, HttpResponse, HttpServer};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tokio::time::interval;
+use tracing::{debug, error, info};
+use tracing_actix_web::TracingLogger;
+
+pub mod archive;
+pub mod changes;
+pub mod config;
+pub mod drift;
+mod health;
+mod history;
+pub mod lifecycle;
+pub mod line;
+pub mod logging;
+mod metrics;
+pub mod period;
+pub mod refresh;
+pub mod rules;
+pub mod scrape;
+pub mod segment;
+pub mod source;
+pub mod stations;
+mod sse;
+mod station_status;
+pub mod store;
+mod v2;
+pub mod webhooks;
+mod websocket;
+
+use changes::{ChangeFeed, ChangesQuery, StatusUpdate};
+use config::Config;
+use health::ScrapeHealth;
+use history::HistoryQuery;
+use lifecycle::{Disruption, Lifecycle};
+use line::Line;
+use period::Period;
+use refresh::{Refresher, StatusQuery};
+use rules::RuleSet;
+use scrape::Scrape;
+use segment::Segment;
+use source::DisruptionSource;
+use stations::Station;
+use store::Store;
+
+/// How long resolved disruptions are still listed by `/disruptions`.
+const RECENTLY_RESOLVED_HOURS: i64 = 24;
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ElevatorStatus {
+    pub id: String,
+    /// As scraped; see `station_id` for the station it was matched to.
+    pub station: String,
+    /// `Station::id` from the registry, `None` if the name wasn't recognized.
+    #[serde(default)]
+    pub station_id: Option<String>,
+    pub event: String,
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    pub start_time: Option<DateTime<Utc>>,
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    pub end_time: Option<DateTime<Utc>>,
+    pub period: Period,
+    pub raw_period: String,
+    pub location: String,
+    pub info: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct SchwebebahnDisruption {
+    pub id: String,
+    pub event: String,
+    pub location: String,
+    /// The stations `location` names, if any.
+    #[serde(default)]
+    pub segment: Option<Segment>,
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    pub start_time: Option<DateTime<Utc>>,
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    pub end_time: Option<DateTime<Utc>>,
+    pub period: Period,
+    pub raw_period: String,
+    pub info: String,
+}
+
+impl ElevatorStatus {
+    /// The entry the v1 `elevators` list holds when no elevator is out of service.
+    fn placeholder() -> ElevatorStatus {
+        ElevatorStatus {
+            id: String::new(),
+            station: String::new(),
+            station_id: None,
+            event: "Keine Störungen".to_string(),
+            start_time: None,
+            end_time: None,
+            period: Period::Unknown,
+            raw_period: String::new(),
+            location: String::new(),
+            info: "Alle Aufzüge sind in Betrieb".to_string(),
+        }
+    }
+}
+
+impl SchwebebahnDisruption {
+    /// Whether the disruption's segment includes `station`, or its location
+    /// names it in a way the segment parser didn't pick up.
+    pub fn affects(&self, station: &Station) -> bool {
+        self.segment.as_ref().is_some_and(|segment| segment.contains(station)) || station.is_mentioned_in(&self.location)
+    }
+
+    /// The `"{event}: {location}"` form served in the v1 `schwebebahn` list.
+    fn summary(&self) -> String {
+        format!("{}: {}", self.event, self.location)
+    }
+}
+
+/// The scraped lists as they are, without placeholders. It serialises in the
+/// v1 format `/status`, the streams and the scrape history have always
+/// served, see `StatusV1`; `/v2/status` builds its own.
+#[derive(Clone, Deserialize, Debug, Default)]
+pub struct Status {
+    schwebebahn_disruptions: Vec<SchwebebahnDisruption>,
+    /// Elevators out of service.
+    #[serde(deserialize_with = "without_placeholder")]
+    elevators: Vec<ElevatorStatus>,
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    last_updated: Option<DateTime<Utc>>,
+    #[serde(default)]
+    health: ScrapeHealth,
+    /// Derived from `schwebebahn_disruptions` and `health` when serving.
+    #[serde(default)]
+    line: Line,
+    /// Change feed cursor of the last change this status reflects. It is
+    /// swapped in together with the status, so a stream that starts from a
+    /// snapshot resumes the feed right after it.
+    #[serde(skip)]
+    cursor: i64,
+}
+
+/// `Status` in the v1 format: the disruptions again as `schwebebahn`
+/// summaries, and a placeholder entry in each list when it is empty, as
+/// existing consumers expect.
+#[derive(Serialize)]
+struct StatusV1<'a> {
+    schwebebahn: Vec<String>,
+    schwebebahn_disruptions: &'a [SchwebebahnDisruption],
+    elevators: Cow<'a, [ElevatorStatus]>,
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    last_updated: Option<DateTime<Utc>>,
+    health: &'a ScrapeHealth,
+    line: &'a Line,
+}
+
+impl Serialize for Status {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let schwebebahn = if self.schwebebahn_disruptions.is_empty() {
+            vec!["Keine aktuellen Störungen".to_string()]
+        } else {
+            self.schwebebahn_disruptions.iter().map(SchwebebahnDisruption::summary).collect()
+        };
+        let elevators = if self.elevators.is_empty() {
+            Cow::Owned(vec![ElevatorStatus::placeholder()])
+        } else {
+            Cow::Borrowed(&self.elevators[..])
+        };
+        StatusV1 {
+            schwebebahn,
+            schwebebahn_disruptions: &self.schwebebahn_disruptions,
+            elevators,
+            last_updated: self.last_updated,
+            health: &self.health,
+            line: &self.line,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Reads `elevators` back from a recorded v1 `Status`, which holds the
+/// placeholder when no elevator was out of service.
+fn without_placeholder<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<ElevatorStatus>, D::Error> {
+    let placeholder = ElevatorStatus::placeholder();
+    let mut elevators = Vec::<ElevatorStatus>::deserialize(deserializer)?;
+    elevators.retain(|elevator| *elevator != placeholder);
+    Ok(elevators)
+}
+
+pub struct AppState {
+    config: Config,
+    last_api_request: Mutex<Option<DateTime<Utc>>>,
+    status: Mutex<Status>,
+    store: Store,
+    updates: broadcast::Sender<StatusUpdate>,
+    refresher: Refresher,
+    rules: RuleSet,
+    source: Box<dyn DisruptionSource>,
+}
+
+/// Builds the `Status` served to clients from one scrape.
+pub fn status_from_scrape(now: DateTime<Utc>, scrape: Scrape) -> Status {
+    let Scrape { schwebebahn, elevators, warnings, rows, .. } = scrape;
+    let mut health = ScrapeHealth::default();
+    health.record_success(now, rows, warnings.iter().map(ToString::to_string).collect());
+    let line = line::classify(&schwebebahn, now);
+
+    Status {
+        schwebebahn_disruptions: schwebebahn,
+        elevators,
+        last_updated: Some(now),
+        health,
+        line,
+        cursor: 0,
+    }
+}
+
+fn apply_scrape(state: &AppState, now: DateTime<Utc>, scrape: Scrape) {
+    metrics::set_active(&scrape.schwebebahn, &scrape.elevators);
+
+    let mut disruptions: Vec<Disruption> = scrape.schwebebahn.iter().cloned().map(Disruption::Subway).collect();
+    disruptions.extend(scrape.elevators.iter().cloned().map(Disruption::Elevator));
+    let changes = match state.store.track(now, &disruptions) {
+        Ok(changes) => changes,
+        Err(e) => {
+            error!(error = %e, "Error tracking disruptions");
+            Vec::new()
+        }
+    };
+
+    let mut new_status = status_from_scrape(now, scrape);
+    info!(
+        schwebebahn = new_status.schwebebahn_disruptions.len(),
+        elevators = new_status.elevators.len(),
+        "Status updated"
+    );
+    debug!(status = ?new_status);
+    if let Err(e) = state.store.record_scrape(now, &new_status) {
+        error!(error = %e, "Error recording status");
+    }
+
+    {
+        let mut status = state.status.lock().unwrap();
+        new_status.cursor = changes.last().map_or(status.cursor, |change| change.cursor);
+        *status = new_status.clone();
+    }
+
+    if let Some(last) = changes.last() {
+        info!(changes = changes.len(), cursor = last.cursor, "Disruption changes recorded");
+        // Nobody listening is fine, the changes are in the store.
+        let _ = state.updates.send(StatusUpdate { cursor: last.cursor, status: new_status, changes });
+    }
+}
+
+/// Serves the current `Status`. Stale data triggers a refresh; the request
+/// waits for it up to `?wait=<seconds>`, or by default when nothing has been
+/// scraped yet, so the first request after startup isn't answered empty.
+async fn status(data: web::Data<Arc<AppState>>, query: web::Query<StatusQuery>) -> HttpResponse {
+    HttpResponse::Ok().json(requested_status(&data, &query).await)
+}
+
+/// `current_status` for a status request, refreshed first as `status`
+/// describes.
+async fn requested_status(data: &Arc<AppState>, query: &StatusQuery) -> Status {
+    metrics::STATUS_REQUESTS.inc();
+    let _timer = metrics::STATUS_REQUEST_DURATION.start_timer();
+    *data.last_api_request.lock().unwrap() = Some(Utc::now());
+
+    if refresh::is_stale(data) {
+        let never_scraped = data.status.lock().unwrap().last_updated.is_none();
+        let wait = query.wait.or(never_scraped.then_some(data.config.max_wait_seconds));
+
+        let state = Arc::clone(data);
+        let refreshing = tokio::spawn(async move { refresh::refresh(&state).await });
+        if let Some(wait) = wait {
+            let wait = std::time::Duration::from_secs(wait.min(data.config.max_wait_seconds));
+            let _ = tokio::time::timeout(wait, refreshing).await;
+        }
+    }
+
+    current_status(data)
+}
+
+/// A copy of the current `Status` with the computed fields filled in.
+fn current_status(state: &AppState) -> Status {
+    let mut status = state.status.lock().unwrap().clone();
+    status.health.stale = health::is_stale(status.last_updated, state.config.stale_after_minutes);
+    status.line = if status.health.parser_broken {
+        Line::unknown("Die Störungsseite kann derzeit nicht gelesen werden")
+    } else if status.last_updated.is_none() {
+        Line::unknown("Noch keine Daten")
+    } else if status.health.stale {
+        Line::unknown("Die Daten sind veraltet")
+    } else {
+        line::classify(&status.schwebebahn_disruptions, Utc::now())
+    };
+    status
+}
+
+async fn disruptions(data: web::Data<Arc<AppState>>) -> HttpResponse {
+    let since = Utc::now() - Duration::hours(RECENTLY_RESOLVED_HOURS);
+    let lifecycle = data.store.active_disruptions().and_then(|active| {
+        let recently_resolved = data.store.resolved_since(since)?;
+        Ok(Lifecycle { active, recently_resolved })
+    });
+
+    match lifecycle {
+        Ok(lifecycle) => HttpResponse::Ok().json(lifecycle),
+        Err(e) => {
+            error!(error = %e, "Error loading disruptions");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+async fn disruption_history(data: web::Data<Arc<AppState>>, query: web::Query<HistoryQuery>) -> HttpResponse {
+    match data.store.disruption_history(&query) {
+        Ok(page) => HttpResponse::Ok().json(page),
+        Err(e) => {
+            error!(error = %e, "Error loading disruption history");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+async fn scrape_history(data: web::Data<Arc<AppState>>, query: web::Query<HistoryQuery>) -> HttpResponse {
+    match data.store.scrape_history(&query) {
+        Ok(page) => HttpResponse::Ok().json(page),
+        Err(e) => {
+            error!(error = %e, "Error loading scrape history");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+async fn changes(data: web::Data<Arc<AppState>>, query: web::Query<ChangesQuery>) -> HttpResponse {
+    let since = query.since();
+    let limit = query.limit();
+    let feed = data.store.changes_since(since, limit).and_then(|changes| {
+        let latest = data.store.latest_cursor()?;
+        let cursor = changes.last().map_or(since.max(0), |change| change.cursor);
+        Ok(ChangeFeed { cursor, has_more: cursor < latest, changes })
+    });
+
+    match feed {
+        Ok(feed) => HttpResponse::Ok().json(feed),
+        Err(e) => {
+            error!(error = %e, "Error loading changes");
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+impl AppState {
+    /// Opens the store and restores the last recorded status from it.
+    pub fn new(config: Config, rules: RuleSet, source: Box<dyn DisruptionSource>) -> Result<AppState, Box<dyn std::error::Error>> {
+        let store = Store::open(&config.database_path)?;
+        let mut restored = store.latest_status().unwrap_or_else(|e| {
+            error!(error = %e, "Error restoring status");
+            None
+        }).unwrap_or_default();
+        restored.cursor = store.latest_cursor()?;
+
+        Ok(AppState {
+            config,
+            last_api_request: Mutex::new(None),
+            status: Mutex::new(restored),
+            store,
+            updates: broadcast::channel(16).0,
+            refresher: Refresher::default(),
+            rules,
+            source,
+        })
+    }
+}
+
+/// Scrapes right away, then every `scrape_interval_minutes` while there is
+/// interest in the data.
+pub async fn poll(state: Arc<AppState>) {
+    refresh::refresh(&state).await;
+
+    let mut interval = interval(Duration::minutes(state.config.scrape_interval_minutes as i64).to_std().unwrap());
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        if should_check(&state) {
+            refresh::refresh(&state).await;
+        }
+    }
+}
+
+pub fn routes(config: &mut web::ServiceConfig) {
+    config
+        .route("/status", web::get().to(status))
+        .route("/v2/status", web::get().to(v2::status))
+        .route("/health", web::get().to(health::health))
+        .route("/metrics", web::get().to(metrics::metrics))
+        .route("/status/stream", web::get().to(sse::status_stream))
+        .route("/ws", web::get().to(websocket::websocket))
+        .route("/disruptions", web::get().to(disruptions))
+        .route("/changes", web::get().to(changes))
+        .route("/history", web::get().to(disruption_history))
+        .route("/history/scrapes", web::get().to(scrape_history))
+        .route("/stations", web::get().to(station_status::stations))
+        .route("/stations/{id}", web::get().to(station_status::station));
+}
+
+/// Starts the background tasks and serves the API on `Config::bind`.
+pub async fn run(state: AppState) -> std::io::Result<()> {
+    let state = Arc::new(state);
+    let bind = state.config.bind.clone();
+
+    let state_clone = Arc::clone(&state);
+    tokio::spawn(async move { state_clone.rules.watch().await });
+    tokio::spawn(webhooks::dispatch(Arc::clone(&state), state.config.webhooks.clone()));
+    tokio::spawn(poll(Arc::clone(&state)));
+
+    HttpServer::new(move || {
+        App::new()
+            .wrap(TracingLogger::default())
+            .app_data(web::Data::new(Arc::clone(&state)))
+            .configure(routes)
+    })
+    .bind(bind)?
+    .run()
+    .await
+}
+
+fn should_check(state: &Arc<AppState>) -> bool {
+    // Open streams count as ongoing interest.
+    if state.updates.receiver_count() > 0 {
+        return true;
+    }
+
+    let last_request = state.last_api_request.lock().unwrap();
+    match *last_request {
+        Some(time) => Utc::now() - time < Duration::minutes(state.config.activity_window_minutes as i64),
+        None => false,
+    }
+}
\ No newline at end of file
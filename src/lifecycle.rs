@@ -0,0 +1,109 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::stations;
+use crate::{ElevatorStatus, SchwebebahnDisruption};
+
+/// A single scraped row, tagged with its `data-transportation` value.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "transportation", rename_all = "snake_case")]
+pub enum Disruption {
+    Subway(SchwebebahnDisruption),
+    Elevator(ElevatorStatus),
+}
+
+impl Disruption {
+    /// Identifies the same disruption across scrapes: the row `id` attribute
+    /// when WSW provides one, otherwise a hash of the row's content.
+    pub fn key(&self) -> String {
+        let id = match self {
+            Disruption::Subway(disruption) => &disruption.id,
+            Disruption::Elevator(elevator) => &elevator.id,
+        };
+        if !id.is_empty() {
+            return id.clone();
+        }
+
+        let mut hasher = Sha256::new();
+        for field in [self.transportation(), self.station(), self.event(), self.location(), self.raw_period()] {
+            hasher.update(field.as_bytes());
+            hasher.update([0]);
+        }
+        format!("sha256:{}", hex::encode(hasher.finalize()))
+    }
+
+    pub fn transportation(&self) -> &'static str {
+        match self {
+            Disruption::Subway(_) => "subway",
+            Disruption::Elevator(_) => "elevator",
+        }
+    }
+
+    /// Subway rows aren't tied to a single station, so this is empty for them.
+    pub fn station(&self) -> &str {
+        match self {
+            Disruption::Subway(_) => "",
+            Disruption::Elevator(elevator) => &elevator.station,
+        }
+    }
+
+    pub fn event(&self) -> &str {
+        match self {
+            Disruption::Subway(disruption) => &disruption.event,
+            Disruption::Elevator(elevator) => &elevator.event,
+        }
+    }
+
+    pub fn location(&self) -> &str {
+        match self {
+            Disruption::Subway(disruption) => &disruption.location,
+            Disruption::Elevator(elevator) => &elevator.location,
+        }
+    }
+
+    /// Whether an elevator is at `station`, or a subway disruption affects it.
+    /// `station` is resolved against the registry, so ids, names and aliases
+    /// all work; unknown names are compared ignoring case.
+    pub fn mentions_station(&self, station: &str) -> bool {
+        if let Some(known) = stations::lookup(station) {
+            return match self {
+                Disruption::Subway(disruption) => disruption.affects(known),
+                Disruption::Elevator(elevator) => elevator.station_id.as_deref() == Some(known.id),
+            };
+        }
+
+        let station = station.trim().to_lowercase();
+        match self {
+            Disruption::Subway(disruption) => disruption.location.to_lowercase().contains(&station),
+            Disruption::Elevator(elevator) => elevator.station.to_lowercase() == station,
+        }
+    }
+
+    pub fn raw_period(&self) -> &str {
+        match self {
+            Disruption::Subway(disruption) => &disruption.raw_period,
+            Disruption::Elevator(elevator) => &elevator.raw_period,
+        }
+    }
+}
+
+/// A disruption together with when we first and last saw it on the page.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TrackedDisruption {
+    pub key: String,
+    pub disruption: Disruption,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub first_seen: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub last_seen: DateTime<Utc>,
+    /// Set by the first scrape that no longer contains the disruption.
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Lifecycle {
+    pub active: Vec<TrackedDisruption>,
+    pub recently_resolved: Vec<TrackedDisruption>,
+}
@@ -0,0 +1,137 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::segment::{Direction, Segment};
+use crate::stations;
+use crate::SchwebebahnDisruption;
+
+/// How the Schwebebahn as a whole is running, ordered from best to worst
+/// apart from `Unknown`.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, PartialOrd)]
+#[serde(rename_all = "snake_case")]
+pub enum LineState {
+    Normal,
+    /// A segment is closed, a station is skipped or a shuttle runs.
+    PartialService,
+    /// No service on the whole line.
+    Suspended,
+    /// Never scraped, stale data or a page the parser can't read.
+    #[default]
+    Unknown,
+}
+
+/// `Status.line`: the state plus a short German reason for display.
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Line {
+    pub state: LineState,
+    pub reason: String,
+}
+
+impl Line {
+    pub fn unknown(reason: &str) -> Line {
+        Line { state: LineState::Unknown, reason: reason.to_string() }
+    }
+}
+
+/// Words in a row's event or info text meaning trains don't run.
+const CLOSED: [&str; 9] = [
+    "betriebsunterbrechung",
+    "betriebseinstellung",
+    "eingestellt",
+    "gesperrt",
+    "sperrung",
+    "kein betrieb",
+    "fährt nicht",
+    "ersatzverkehr",
+    "keine fahrten",
+];
+/// Words meaning a station is served without stopping.
+const SKIPPED: [&str; 3] = ["entfällt", "ohne halt", "durchfahren"];
+const SHUTTLE: [&str; 2] = ["pendelverkehr", "pendelbetrieb"];
+
+/// Classifies the line from the subway rows in effect at `now`; rows only
+/// announced for later don't count yet.
+pub fn classify(disruptions: &[SchwebebahnDisruption], now: DateTime<Utc>) -> Line {
+    let mut state = LineState::Normal;
+    let mut reasons = Vec::new();
+    let mut notices = Vec::new();
+    for disruption in disruptions.iter().filter(|disruption| in_effect(disruption, now)) {
+        let (row_state, reason) = classify_row(disruption);
+        if row_state == LineState::Normal {
+            notices.push(format!("{}: {}", disruption.event, disruption.location));
+            continue;
+        }
+        if row_state > state {
+            state = row_state;
+            reasons.clear();
+        }
+        if row_state == state && !reasons.contains(&reason) {
+            reasons.push(reason);
+        }
+    }
+
+    let reason = match state {
+        LineState::Normal if notices.is_empty() => "Regulärer Betrieb".to_string(),
+        LineState::Normal => format!("Regulärer Betrieb ({})", notices.join("; ")),
+        _ => reasons.join("; "),
+    };
+    Line { state, reason }
+}
+
+fn classify_row(disruption: &SchwebebahnDisruption) -> (LineState, String) {
+    let text = format!("{} {}", disruption.event, disruption.info).to_lowercase();
+    let mentions = |words: &[&str]| words.iter().any(|word| text.contains(word));
+    let segment = disruption.segment.as_ref();
+
+    if mentions(&SHUTTLE) {
+        let reason = match segment {
+            Some(segment) => format!("Pendelverkehr {}", describe(segment)),
+            None => format!("Pendelverkehr: {}", disruption.location),
+        };
+        return (LineState::PartialService, reason);
+    }
+    if mentions(&CLOSED) {
+        return match segment {
+            Some(segment) if whole_line(segment) && segment.direction.is_none() => {
+                (LineState::Suspended, format!("Kein Betrieb auf der Gesamtstrecke ({})", disruption.event))
+            }
+            Some(segment) => (LineState::PartialService, format!("Kein Betrieb {}", describe(segment))),
+            None => (LineState::PartialService, format!("{}: {}", disruption.event, disruption.location)),
+        };
+    }
+    if mentions(&SKIPPED) {
+        if let Some(segment) = segment.filter(|segment| segment.from == segment.to) {
+            return (LineState::PartialService, format!("Kein Halt an {}", name(&segment.from)));
+        }
+    }
+    (LineState::Normal, String::new())
+}
+
+fn in_effect(disruption: &SchwebebahnDisruption, now: DateTime<Utc>) -> bool {
+    disruption.start_time.is_none_or(|start| start <= now) && disruption.end_time.is_none_or(|end| end > now)
+}
+
+fn whole_line(segment: &Segment) -> bool {
+    segment.stations().count() == stations::STATIONS.len()
+}
+
+/// `zwischen Ohligsmühle und Kluse in Richtung Oberbarmen`, or `an Kluse`.
+fn describe(segment: &Segment) -> String {
+    let mut text = if segment.from == segment.to {
+        format!("an {}", name(&segment.from))
+    } else if whole_line(segment) {
+        "auf der Gesamtstrecke".to_string()
+    } else {
+        format!("zwischen {} und {}", name(&segment.from), name(&segment.to))
+    };
+    match segment.direction {
+        Some(Direction::TowardsVohwinkel) => text.push_str(" in Richtung Vohwinkel"),
+        Some(Direction::TowardsOberbarmen) => text.push_str(" in Richtung Oberbarmen"),
+        None => {}
+    }
+    text
+}
+
+fn name(id: &str) -> &str {
+    stations::by_id(id).map_or(id, |station| station.name)
+}
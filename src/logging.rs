@@ -0,0 +1,34 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::EnvFilter;
+
+use crate::config::Config;
+
+#[derive(Clone, Copy, Deserialize, Debug, PartialEq, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Human readable lines.
+    Text,
+    /// One JSON object per event, including the fields of its spans.
+    Json,
+}
+
+/// Installs the global subscriber. Spans opened by `TracingLogger` carry the
+/// request id and the `scrape` spans the attempt number, so every event can
+/// be attributed to the request or scrape it happened in. Closing a span is
+/// logged too, which gives one line per request with its status and timing.
+pub fn init(config: &Config) {
+    let filter = EnvFilter::new(&config.log_filter);
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_span_events(FmtSpan::CLOSE);
+    match config.log_format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber.json().with_current_span(true).with_span_list(true).init(),
+    }
+}
+
+pub fn validate_filter(filter: &str) -> Result<(), String> {
+    EnvFilter::try_new(filter).map(|_| ()).map_err(|e| e.to_string())
+}
@@ -0,0 +1,104 @@
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use prometheus::{
+    register_histogram, register_int_counter, register_int_counter_vec, register_int_gauge, register_int_gauge_vec,
+    Encoder, Histogram, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, TextEncoder,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock};
+use tracing::error;
+
+use crate::{AppState, ElevatorStatus, SchwebebahnDisruption};
+
+pub static SCRAPE_DURATION: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(
+        "schwebebahn_scrape_duration_seconds",
+        "Time spent fetching and parsing the WSW page.",
+        vec![0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]
+    )
+    .unwrap()
+});
+
+pub static SCRAPES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "schwebebahn_scrapes_total",
+        "Scrape attempts by result and, for failures, error kind.",
+        &["result", "kind"]
+    )
+    .unwrap()
+});
+
+pub static UPSTREAM_RESPONSES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "schwebebahn_upstream_responses_total",
+        "HTTP status codes returned by the WSW page.",
+        &["code"]
+    )
+    .unwrap()
+});
+
+pub static ACTIVE_DISRUPTIONS: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "schwebebahn_active_disruptions",
+        "Disruptions in the current data; station is the registry id, `unknown` for unresolved names and empty for subway rows.",
+        &["transportation", "station"]
+    )
+    .unwrap()
+});
+
+pub static STATUS_REQUESTS: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!("schwebebahn_status_requests_total", "Requests served by /status.").unwrap()
+});
+
+pub static STATUS_REQUEST_DURATION: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(
+        "schwebebahn_status_request_duration_seconds",
+        "Time to answer /status, including any wait for a refresh.",
+        vec![0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0]
+    )
+    .unwrap()
+});
+
+pub static PARSER_BROKEN: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "schwebebahn_parser_broken",
+        "1 while the WSW page no longer matches what the parser expects."
+    )
+    .unwrap()
+});
+
+static DATA_AGE: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "schwebebahn_data_age_seconds",
+        "Seconds since the data was last scraped successfully, -1 if never."
+    )
+    .unwrap()
+});
+
+/// Replaces the per-station gauges with the counts of one scrape.
+pub fn set_active(schwebebahn: &[SchwebebahnDisruption], elevators: &[ElevatorStatus]) {
+    ACTIVE_DISRUPTIONS.reset();
+    ACTIVE_DISRUPTIONS.with_label_values(&["subway", ""]).set(schwebebahn.len() as i64);
+
+    let mut by_station: HashMap<&str, i64> = HashMap::new();
+    for elevator in elevators {
+        *by_station.entry(elevator.station_id.as_deref().unwrap_or("unknown")).or_default() += 1;
+    }
+    for (station, count) in by_station {
+        ACTIVE_DISRUPTIONS.with_label_values(&["elevator", station]).set(count);
+    }
+}
+
+/// `/metrics` in the Prometheus text format.
+pub async fn metrics(data: web::Data<Arc<AppState>>) -> HttpResponse {
+    let last_updated = data.status.lock().unwrap().last_updated;
+    DATA_AGE.set(last_updated.map_or(-1, |time| (Utc::now() - time).num_seconds()));
+
+    let mut buffer = Vec::new();
+    let encoder = TextEncoder::new();
+    if let Err(e) = encoder.encode(&prometheus::gather(), &mut buffer) {
+        error!(error = %e, "Error encoding metrics");
+        return HttpResponse::InternalServerError().finish();
+    }
+    HttpResponse::Ok().content_type(encoder.format_type()).body(buffer)
+}
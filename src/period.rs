@@ -0,0 +1,214 @@
+use chrono::{DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Europe::Berlin;
+use serde::{Deserialize, Serialize};
+
+/// One end of a disruption period. WSW sometimes only gives a day, which we
+/// keep as a date instead of inventing a time of day.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "precision", content = "value", rename_all = "snake_case")]
+pub enum Moment {
+    DateTime(#[serde(with = "chrono::serde::ts_seconds")] DateTime<Utc>),
+    Date(NaiveDate),
+}
+
+impl Moment {
+    /// The first instant covered by this moment.
+    pub fn earliest(&self) -> DateTime<Utc> {
+        match *self {
+            Moment::DateTime(time) => time,
+            Moment::Date(date) => berlin_to_utc(date.and_time(NaiveTime::MIN)),
+        }
+    }
+
+    /// The first instant after this moment, so `bis 20.10.2024` covers the whole day.
+    pub fn latest(&self) -> DateTime<Utc> {
+        match *self {
+            Moment::DateTime(time) => time,
+            Moment::Date(date) => berlin_to_utc((date + Duration::days(1)).and_time(NaiveTime::MIN)),
+        }
+    }
+}
+
+/// The parsed contents of a `td.cell-period` cell.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Period {
+    /// `06.05.2024 08:00 Uhr bis 06.05.2024 17:00 Uhr`
+    Range { start: Moment, end: Moment },
+    /// `ab 06.05.2024`
+    From { start: Moment },
+    /// `bis 06.05.2024`
+    Until { end: Moment },
+    /// `06.05.2024 bis auf Weiteres`, the start is optional.
+    UntilFurtherNotice { start: Option<Moment> },
+    /// Empty or not understood; the text is still available as `raw_period`.
+    Unknown,
+}
+
+impl Period {
+    pub fn start_time(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Period::Range { start, .. } | Period::From { start } => Some(start.earliest()),
+            Period::UntilFurtherNotice { start } => start.map(|start| start.earliest()),
+            Period::Until { .. } | Period::Unknown => None,
+        }
+    }
+
+    pub fn end_time(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Period::Range { end, .. } | Period::Until { end } => Some(end.latest()),
+            _ => None,
+        }
+    }
+}
+
+/// A date and/or time as it appears in the page, before the timezone is applied.
+#[derive(Clone, Copy)]
+enum Partial {
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+    Time(NaiveTime),
+}
+
+pub fn parse_period(period: &str) -> Period {
+    let text = period.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        return Period::Unknown;
+    }
+
+    let (start_text, end_text) = match find_word(&text, "bis") {
+        Some(index) => (&text[..index], Some(&text[index + "bis".len()..])),
+        None => (text.as_str(), None),
+    };
+
+    let start_text = strip_prefix_word(start_text.trim(), &["ab", "seit", "vom", "von"]);
+    let start = if start_text.is_empty() { None } else { parse_partial(start_text) };
+
+    let end_text = match end_text {
+        Some(end_text) => end_text.trim(),
+        None => {
+            return match start {
+                Some(start) => match to_moment(start, None) {
+                    Some(start) => Period::From { start },
+                    None => Period::Unknown,
+                },
+                None => Period::Unknown,
+            };
+        }
+    };
+
+    if end_text.to_lowercase().starts_with("auf weiteres") {
+        let start = start.and_then(|start| to_moment(start, None));
+        return Period::UntilFurtherNotice { start };
+    }
+
+    let end = match parse_partial(end_text) {
+        Some(end) => end,
+        None => return Period::Unknown,
+    };
+
+    // `06.05.2024 08:00 bis 17:00 Uhr` only names the day once.
+    let start_date = match start {
+        Some(Partial::Date(date)) => Some(date),
+        Some(Partial::DateTime(time)) => Some(time.date()),
+        _ => None,
+    };
+    let start = start.and_then(|start| to_moment(start, None));
+    let mut end_moment = to_moment(end, start_date);
+    // An end time that would come before the start, as in the overnight
+    // `06.05.2024 22:00 bis 04:00 Uhr`, is on the next day.
+    if let (Partial::Time(_), Some(start), Some(date)) = (end, start, start_date) {
+        if end_moment.is_some_and(|end_moment| end_moment.latest() < start.earliest()) {
+            end_moment = to_moment(end, Some(date + Duration::days(1)));
+        }
+    }
+
+    match (start, end_moment) {
+        (Some(start), Some(end)) => Period::Range { start, end },
+        (None, Some(end)) if start_text.is_empty() => Period::Until { end },
+        _ => Period::Unknown,
+    }
+}
+
+fn to_moment(partial: Partial, date: Option<NaiveDate>) -> Option<Moment> {
+    match partial {
+        Partial::Date(date) => Some(Moment::Date(date)),
+        Partial::DateTime(time) => Some(Moment::DateTime(berlin_to_utc(time))),
+        Partial::Time(time) => date.map(|date| Moment::DateTime(berlin_to_utc(date.and_time(time)))),
+    }
+}
+
+fn parse_partial(text: &str) -> Option<Partial> {
+    let mut date = None;
+    let mut time = None;
+
+    for token in text.split(|c: char| c.is_whitespace() || c == ',') {
+        let token = token.trim_end_matches('.');
+        if token.is_empty() || token.eq_ignore_ascii_case("uhr") || token.eq_ignore_ascii_case("um") {
+            continue;
+        }
+        if date.is_none() {
+            if let Some(parsed) = parse_date(token) {
+                date = Some(parsed);
+                continue;
+            }
+        }
+        if time.is_none() {
+            if let Ok(parsed) = NaiveTime::parse_from_str(token, "%H:%M") {
+                time = Some(parsed);
+                continue;
+            }
+        }
+        return None;
+    }
+
+    match (date, time) {
+        (Some(date), Some(time)) => Some(Partial::DateTime(date.and_time(time))),
+        (Some(date), None) => Some(Partial::Date(date)),
+        (None, Some(time)) => Some(Partial::Time(time)),
+        (None, None) => None,
+    }
+}
+
+fn parse_date(token: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(token, "%d.%m.%Y")
+        .ok()
+        .filter(|date| date.year() >= 1000)
+        .or_else(|| NaiveDate::parse_from_str(token, "%d.%m.%y").ok())
+}
+
+/// Interprets a wall-clock time in Europe/Berlin. Ambiguous times at the end of
+/// summer time resolve to the earlier instant, and times inside the spring gap
+/// are moved forward by the skipped hour.
+pub fn berlin_to_utc(local: NaiveDateTime) -> DateTime<Utc> {
+    match Berlin.from_local_datetime(&local) {
+        LocalResult::Single(time) => time.with_timezone(&Utc),
+        LocalResult::Ambiguous(earliest, _) => earliest.with_timezone(&Utc),
+        LocalResult::None => berlin_to_utc(local + Duration::hours(1)),
+    }
+}
+
+/// Byte offset of `word` in `text` as a whole word, ignoring ASCII case.
+fn find_word(text: &str, word: &str) -> Option<usize> {
+    let lower = text.to_ascii_lowercase();
+    let mut offset = 0;
+    while let Some(found) = lower[offset..].find(word) {
+        let index = offset + found;
+        let before = lower[..index].chars().next_back();
+        let after = lower[index + word.len()..].chars().next();
+        if !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric) {
+            return Some(index);
+        }
+        offset = index + word.len();
+    }
+    None
+}
+
+fn strip_prefix_word<'a>(text: &'a str, words: &[&str]) -> &'a str {
+    for word in words {
+        if find_word(text, word) == Some(0) {
+            return text[word.len()..].trim_start();
+        }
+    }
+    text
+}
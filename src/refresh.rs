@@ -0,0 +1,119 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::{error, info_span, Instrument};
+
+use crate::archive::Snapshot;
+use crate::scrape::{parse_snapshot, ScrapeError};
+use crate::store::Store;
+use crate::{apply_scrape, drift, metrics, AppState};
+
+const RETRY_AFTER_SECONDS: i64 = 30;
+
+/// Single-flight guard around scraping: the background loop and any
+/// number of requests that find the data stale share one upstream fetch.
+#[derive(Default)]
+pub struct Refresher {
+    lock: tokio::sync::Mutex<()>,
+    /// Finished scrape attempts, successful or not.
+    attempts: AtomicU64,
+}
+
+/// Query string of `/status` and `/v2/status`.
+#[derive(Deserialize, Debug)]
+pub struct StatusQuery {
+    /// Seconds to wait for a refresh if the data is stale, capped at
+    /// `max_wait_seconds`.
+    pub wait: Option<u64>,
+}
+
+/// Scrapes and applies the result, unless another caller finished a scrape
+/// while this one was waiting for its turn, in which case that result is used.
+pub async fn refresh(state: &AppState) {
+    let refresher = &state.refresher;
+    let seen = refresher.attempts.load(Ordering::SeqCst);
+    let _guard = refresher.lock.lock().await;
+    if refresher.attempts.load(Ordering::SeqCst) != seen {
+        return;
+    }
+
+    scrape_and_apply(state).instrument(info_span!("scrape", attempt = seen + 1)).await;
+    refresher.attempts.fetch_add(1, Ordering::SeqCst);
+}
+
+async fn scrape_and_apply(state: &AppState) {
+    let timer = metrics::SCRAPE_DURATION.start_timer();
+    let snapshot = state.source.fetch().await;
+    if let (Ok(snapshot), true) = (&snapshot, state.source.archive()) {
+        if let Err(e) = state.store.archive_snapshot(snapshot) {
+            error!(error = %e, "Error archiving page");
+        }
+    }
+    process(state, snapshot, Utc::now());
+    timer.observe_duration();
+}
+
+/// Re-parses every page archived in the database at `archive`, in fetch
+/// order, as if each had just been scraped at its `fetched_at`. Tracking,
+/// history and health end up as a live scraper with the current rules
+/// would have recorded them. Returns the number of pages applied.
+pub async fn reparse(state: &AppState, archive: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+    let archive = Store::open_read_only(&archive.to_string_lossy())?;
+    let fetches = archive.archived_fetches()?;
+    for &id in &fetches {
+        let snapshot = archive.archived_snapshot(id)?;
+        let fetched_at = snapshot.fetched_at;
+        process(state, Ok(snapshot), fetched_at);
+    }
+    Ok(fetches.len())
+}
+
+/// Parses and checks a fetched page and applies it as of `now`, or records
+/// the failure.
+fn process(state: &AppState, snapshot: Result<Snapshot, ScrapeError>, now: DateTime<Utc>) {
+    let rules = state.rules.current();
+    let result = snapshot.and_then(|snapshot| parse_snapshot(&snapshot, &rules));
+    let result = result.and_then(|scrape| drift::check(&scrape, &state.store, now).map(|()| scrape));
+
+    match result {
+        Ok(scrape) => {
+            metrics::SCRAPES.with_label_values(&["success", ""]).inc();
+            metrics::PARSER_BROKEN.set(0);
+            apply_scrape(state, now, scrape);
+        }
+        Err(e) => {
+            metrics::SCRAPES.with_label_values(&["failure", e.kind()]).inc();
+            error!(error = %e, "Error scraping status");
+            let mut status = state.status.lock().unwrap();
+            let was_broken = status.health.parser_broken;
+            status.health.record_failure(now, &e);
+            if status.health.parser_broken {
+                metrics::PARSER_BROKEN.set(1);
+                if !was_broken {
+                    error!(
+                        source_url = %state.config.source_url,
+                        "Page structure changed, disruptions are unknown until the parser is updated"
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Missing data or data older than `max_age_minutes`, unless the last
+/// attempt failed less than `RETRY_AFTER_SECONDS` ago, so requests don't
+/// hammer an upstream that is down.
+pub fn is_stale(state: &AppState) -> bool {
+    let now = Utc::now();
+    let status = state.status.lock().unwrap();
+    if let (Some(last_attempt), Some(_)) = (status.health.last_attempt, &status.health.last_error) {
+        if now - last_attempt < Duration::seconds(RETRY_AFTER_SECONDS) {
+            return false;
+        }
+    }
+    match status.last_updated {
+        Some(time) => now - time >= Duration::minutes(state.config.max_age_minutes as i64),
+        None => true,
+    }
+}
@@ -0,0 +1,195 @@
+use scraper::Selector;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{error, info};
+
+/// How often the rules file is checked for modifications.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The scraping rules as written in the rules file. Every field defaults to
+/// what the WSW page currently uses, so a file only needs what changed.
+#[derive(Clone, Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct RulesFile {
+    /// The container the rows live in; a page without it is a redesign.
+    pub table: String,
+    pub row: String,
+    /// Relative to the row, like the other cell selectors.
+    pub station: String,
+    pub event: String,
+    pub period: String,
+    pub location: String,
+    /// Relative to the element carrying the row's `id`.
+    pub info: String,
+    /// Row attribute telling subway and elevator rows apart.
+    pub transportation_attribute: String,
+    pub subway: Vec<String>,
+    pub elevator: Vec<String>,
+}
+
+impl Default for RulesFile {
+    fn default() -> RulesFile {
+        RulesFile {
+            table: "table".to_string(),
+            row: "tr.traffic-information-infos".to_string(),
+            station: "td.cell-line span.fw-bold".to_string(),
+            event: "td.cell-event span.flag".to_string(),
+            period: "td.cell-period".to_string(),
+            location: "td.cell-location".to_string(),
+            info: "p:last-child".to_string(),
+            transportation_attribute: "data-transportation".to_string(),
+            subway: vec!["subway".to_string()],
+            elevator: vec!["elevator".to_string()],
+        }
+    }
+}
+
+/// Validated, compiled rules as used by `scrape::parse_page`.
+#[derive(Debug)]
+pub struct Rules {
+    pub table: Selector,
+    pub row: Selector,
+    pub station: Selector,
+    pub event: Selector,
+    pub period: Selector,
+    pub location: Selector,
+    pub info: Selector,
+    pub transportation_attribute: String,
+    pub subway: Vec<String>,
+    pub elevator: Vec<String>,
+}
+
+impl Rules {
+    /// Collects every problem, like `Config::validate`.
+    pub fn compile(file: &RulesFile) -> Result<Rules, String> {
+        let mut errors = Vec::new();
+        let mut compile = |name: &str, selector: &str| match Selector::parse(selector) {
+            Ok(selector) => Some(selector),
+            Err(e) => {
+                errors.push(format!("{}: {:?} is not a valid selector: {:?}", name, selector, e));
+                None
+            }
+        };
+        let table = compile("table", &file.table);
+        let row = compile("row", &file.row);
+        let station = compile("station", &file.station);
+        let event = compile("event", &file.event);
+        let period = compile("period", &file.period);
+        let location = compile("location", &file.location);
+        let info = compile("info", &file.info);
+
+        if file.transportation_attribute.trim().is_empty() {
+            errors.push("transportation_attribute: must not be empty".to_string());
+        }
+        if file.subway.is_empty() {
+            errors.push("subway: must list at least one value".to_string());
+        }
+        if file.elevator.is_empty() {
+            errors.push("elevator: must list at least one value".to_string());
+        }
+        if let Some(both) = file.subway.iter().find(|value| file.elevator.contains(value)) {
+            errors.push(format!("subway, elevator: {:?} is listed in both", both));
+        }
+
+        match (table, row, station, event, period, location, info) {
+            (Some(table), Some(row), Some(station), Some(event), Some(period), Some(location), Some(info))
+                if errors.is_empty() =>
+            {
+                Ok(Rules {
+                    table,
+                    row,
+                    station,
+                    event,
+                    period,
+                    location,
+                    info,
+                    transportation_attribute: file.transportation_attribute.clone(),
+                    subway: file.subway.clone(),
+                    elevator: file.elevator.clone(),
+                })
+            }
+            _ => Err(errors.join("\n")),
+        }
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules::compile(&RulesFile::default()).expect("built-in rules are valid")
+    }
+}
+
+/// The active `Rules`, reloaded from `path` when the file changes or the
+/// process gets SIGHUP. A file that fails to load or validate is logged and
+/// the previous rules stay active.
+pub struct RuleSet {
+    path: Option<PathBuf>,
+    current: RwLock<Arc<Rules>>,
+    modified: Mutex<Option<SystemTime>>,
+}
+
+impl RuleSet {
+    /// Without a path the built-in rules are used and never reloaded.
+    pub fn load(path: Option<PathBuf>) -> Result<RuleSet, String> {
+        let (rules, modified) = match &path {
+            Some(path) => (read(path)?, modified(path)),
+            None => (Rules::default(), None),
+        };
+        Ok(RuleSet { path, current: RwLock::new(Arc::new(rules)), modified: Mutex::new(modified) })
+    }
+
+    pub fn current(&self) -> Arc<Rules> {
+        Arc::clone(&self.current.read().unwrap())
+    }
+
+    fn reload(&self, path: &Path) {
+        *self.modified.lock().unwrap() = modified(path);
+        match read(path) {
+            Ok(rules) => {
+                *self.current.write().unwrap() = Arc::new(rules);
+                info!(path = %path.display(), "Scraping rules reloaded");
+            }
+            Err(e) => error!(error = %e, "Invalid scraping rules, keeping the active ones"),
+        }
+    }
+
+    /// Reloads on SIGHUP and whenever the file's modification time changes.
+    pub async fn watch(&self) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => hangup,
+            Err(e) => {
+                error!(error = %e, "Error installing SIGHUP handler");
+                return;
+            }
+        };
+        let mut poll = tokio::time::interval(POLL_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = hangup.recv() => self.reload(path),
+                _ = poll.tick() => {
+                    if modified(path) != *self.modified.lock().unwrap() {
+                        self.reload(path);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<Rules, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let file: RulesFile = toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Rules::compile(&file).map_err(|e| format!("{}:\n{}", path.display(), e))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
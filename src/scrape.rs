@@ -0,0 +1,258 @@
+use chrono::Utc;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, StatusCode};
+use scraper::element_ref::ElementRef;
+use scraper::{Html, Selector};
+use std::time::Instant;
+use tracing::{debug, warn};
+
+use crate::archive::Snapshot;
+use crate::metrics;
+use crate::period::parse_period;
+use crate::segment::parse_segment;
+use crate::rules::Rules;
+use crate::stations;
+use crate::{ElevatorStatus, SchwebebahnDisruption};
+
+/// Why a scrape, or a single row of it, could not be used.
+#[derive(Debug, thiserror::Error)]
+pub enum ScrapeError {
+    #[error("request failed: {0}")]
+    Network(#[source] reqwest::Error),
+    #[error("upstream answered with HTTP {0}")]
+    HttpStatus(StatusCode),
+    #[error("response body could not be decoded: {0}")]
+    Decode(#[source] reqwest::Error),
+    #[error("source failed: {0}")]
+    Source(String),
+    #[error("page structure changed: {0}")]
+    StructureChanged(String),
+    /// Reported per row as a warning; the rest of the page is still used.
+    /// `required` is set for cells the row means nothing without, such as
+    /// the event; a missing id or location is normal on real pages.
+    #[error("row {index}{}: {reason}", id.as_deref().map(|id| format!(" ({})", id)).unwrap_or_default())]
+    InvalidRow { index: usize, id: Option<String>, reason: String, required: bool },
+    /// A warning too, but not a sign of a changed page: WSW may list a
+    /// station the registry doesn't know yet.
+    #[error("row {index}: unknown station {name:?}")]
+    UnknownStation { index: usize, name: String },
+}
+
+impl ScrapeError {
+    /// The `kind` label of `schwebebahn_scrapes_total`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ScrapeError::Network(_) => "network",
+            ScrapeError::HttpStatus(_) => "http_status",
+            ScrapeError::Decode(_) => "decode",
+            ScrapeError::Source(_) => "source",
+            ScrapeError::StructureChanged(_) => "structure_changed",
+            ScrapeError::InvalidRow { .. } => "invalid_row",
+            ScrapeError::UnknownStation { .. } => "unknown_station",
+        }
+    }
+}
+
+/// The rows of one page, plus the problems found in individual rows.
+#[derive(Debug, Default)]
+pub struct Scrape {
+    pub schwebebahn: Vec<SchwebebahnDisruption>,
+    pub elevators: Vec<ElevatorStatus>,
+    pub warnings: Vec<ScrapeError>,
+    /// Rows matched on the page, including ones that were ignored.
+    pub rows: usize,
+    /// Rows without a transportation attribute, or with an empty one.
+    pub unlabelled: usize,
+}
+
+/// Downloads the page. Error responses are returned as well so they can be
+/// archived; `parse_snapshot` rejects them.
+#[tracing::instrument(skip(client))]
+pub async fn fetch(client: &Client, url: &str) -> Result<Snapshot, ScrapeError> {
+    let fetched_at = Utc::now();
+    let started = Instant::now();
+    let response = client.get(url).send().await.map_err(ScrapeError::Network)?;
+    metrics::UPSTREAM_RESPONSES.with_label_values(&[response.status().as_str()]).inc();
+    let status = response.status().as_u16();
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let body = response.text().await.map_err(ScrapeError::Decode)?;
+
+    Ok(Snapshot {
+        fetched_at,
+        url: url.to_string(),
+        status,
+        content_type,
+        duration_ms: started.elapsed().as_millis() as u64,
+        body,
+    })
+}
+
+/// Parses a page from any `DisruptionSource`, failing on HTTP error responses.
+pub fn parse_snapshot(snapshot: &Snapshot, rules: &Rules) -> Result<Scrape, ScrapeError> {
+    let status = StatusCode::from_u16(snapshot.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    if !status.is_success() {
+        return Err(ScrapeError::HttpStatus(status));
+    }
+
+    let scrape = parse_page(&snapshot.body, rules)?;
+    for warning in &scrape.warnings {
+        warn!(warning = %warning, "Skipped part of a row");
+    }
+    Ok(scrape)
+}
+
+/// Parses the WSW traffic information page. Problems with single rows are
+/// collected in `Scrape::warnings` instead of failing the whole page; only a
+/// page without the table container is rejected, as it can't be an empty list.
+pub fn parse_page(html: &str, rules: &Rules) -> Result<Scrape, ScrapeError> {
+    let document = Html::parse_document(html);
+    if document.select(&rules.table).next().is_none() {
+        return Err(ScrapeError::StructureChanged("table container not found".to_string()));
+    }
+    let mut scrape = Scrape::default();
+
+    for (index, row) in document.select(&rules.row).enumerate() {
+        scrape.rows += 1;
+        let transportation = row.value().attr(&rules.transportation_attribute).unwrap_or("");
+
+        if rules.elevator.iter().any(|value| value == transportation) {
+            let status = parse_elevator_status(index, &row, &document, rules, &mut scrape.warnings);
+            scrape.elevators.push(status);
+        } else if rules.subway.iter().any(|value| value == transportation) {
+            let disruption = parse_schwebebahn_status(index, &row, &document, rules, &mut scrape.warnings);
+            scrape.schwebebahn.push(disruption);
+        } else {
+            if transportation.trim().is_empty() {
+                scrape.unlabelled += 1;
+            }
+            debug!(index, transportation, "Ignoring row");
+        }
+    }
+
+    Ok(scrape)
+}
+
+#[tracing::instrument(level = "debug", skip_all, fields(id = row.value().attr("id")))]
+fn parse_elevator_status(index: usize, row: &ElementRef, document: &Html, rules: &Rules, warnings: &mut Vec<ScrapeError>) -> ElevatorStatus {
+    let mut cells = RowCells::new(index, row, warnings);
+    let station = cells.text(&rules.station, "station");
+    let event = cells.text(&rules.event, "event");
+    let raw_period = cells.full_text(&rules.period);
+    let location = cells.optional_text(&rules.location, "location");
+    let info = cells.info(document, &rules.info);
+    let id = cells.id.unwrap_or_default();
+
+    let station_id = stations::lookup(&station).map(|station| station.id.to_string());
+    if station_id.is_none() && !station.is_empty() {
+        warnings.push(ScrapeError::UnknownStation { index, name: station.clone() });
+    }
+    let parsed_period = parse_period(&raw_period);
+
+    ElevatorStatus {
+        id,
+        station,
+        station_id,
+        event,
+        start_time: parsed_period.start_time(),
+        end_time: parsed_period.end_time(),
+        period: parsed_period,
+        raw_period,
+        location,
+        info,
+    }
+}
+
+#[tracing::instrument(level = "debug", skip_all, fields(id = row.value().attr("id")))]
+fn parse_schwebebahn_status(index: usize, row: &ElementRef, document: &Html, rules: &Rules, warnings: &mut Vec<ScrapeError>) -> SchwebebahnDisruption {
+    let mut cells = RowCells::new(index, row, warnings);
+    let event = cells.text(&rules.event, "event");
+    let raw_period = cells.full_text(&rules.period);
+    let location = cells.optional_text(&rules.location, "location");
+    let info = cells.info(document, &rules.info);
+
+    let parsed_period = parse_period(&raw_period);
+
+    SchwebebahnDisruption {
+        id: cells.id.unwrap_or_default(),
+        event,
+        segment: parse_segment(&location),
+        location,
+        start_time: parsed_period.start_time(),
+        end_time: parsed_period.end_time(),
+        period: parsed_period,
+        raw_period,
+        info,
+    }
+}
+
+/// Reads the cells of one row, recording a warning for each one that is
+/// missing instead of giving up on the row.
+struct RowCells<'a, 'b> {
+    index: usize,
+    id: Option<String>,
+    row: &'a ElementRef<'a>,
+    warnings: &'b mut Vec<ScrapeError>,
+}
+
+impl<'a, 'b> RowCells<'a, 'b> {
+    fn new(index: usize, row: &'a ElementRef<'a>, warnings: &'b mut Vec<ScrapeError>) -> RowCells<'a, 'b> {
+        let id = row.value().attr("id").map(str::to_string).filter(|id| !id.is_empty());
+        RowCells { index, id, row, warnings }
+    }
+
+    fn warn(&mut self, reason: String, required: bool) {
+        self.warnings.push(ScrapeError::InvalidRow { index: self.index, id: self.id.clone(), reason, required });
+    }
+
+    /// First text node of the first element matching `selector`, trimmed.
+    fn text(&mut self, selector: &Selector, name: &str) -> String {
+        self.cell_text(selector, name, true)
+    }
+
+    /// Like `text`, for a cell the row is still usable without.
+    fn optional_text(&mut self, selector: &Selector, name: &str) -> String {
+        self.cell_text(selector, name, false)
+    }
+
+    fn cell_text(&mut self, selector: &Selector, name: &str, required: bool) -> String {
+        let text = self.row.select(selector).next()
+            .and_then(|el| el.text().next())
+            .unwrap_or("").trim().to_string();
+        if text.is_empty() {
+            self.warn(format!("{} is missing", name), required);
+        }
+        text
+    }
+
+    /// All text of the first element matching `selector`, trimmed. Empty is
+    /// fine here, `parse_period` reports it as unknown.
+    fn full_text(&self, selector: &Selector) -> String {
+        self.row.select(selector).next()
+            .map(|el| el.text().collect::<String>())
+            .unwrap_or_default().trim().to_string()
+    }
+
+    /// The detail paragraph WSW renders for a row: the first `selector` match
+    /// inside the first element carrying the row's `id`. Compared as a plain
+    /// string so ids that aren't valid CSS identifiers work too.
+    fn info(&mut self, document: &Html, selector: &Selector) -> String {
+        let id = match &self.id {
+            Some(id) => id.clone(),
+            None => {
+                self.warn("id is missing, no details available".to_string(), false);
+                return String::new();
+            }
+        };
+
+        document.root_element().descendants()
+            .filter_map(ElementRef::wrap)
+            .filter(|el| el.value().id() == Some(id.as_str()))
+            .find_map(|el| el.select(selector).next())
+            .and_then(|el| el.text().next())
+            .unwrap_or("").trim().to_string()
+    }
+}
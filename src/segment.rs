@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+
+use crate::stations::{self, Station, STATIONS};
+
+/// Which way trains are affected, named after the terminus they head for.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    TowardsVohwinkel,
+    TowardsOberbarmen,
+}
+
+/// The part of the line a subway disruption affects, parsed from its
+/// `td.cell-location` text. `from` and `to` are station ids in line order
+/// and both belong to the segment, so a single station has `from == to`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Segment {
+    pub from: String,
+    pub to: String,
+    /// `None` when both directions are affected or the text doesn't say.
+    pub direction: Option<Direction>,
+}
+
+impl Segment {
+    /// The stations from `from` to `to`, in line order.
+    pub fn stations(&self) -> impl Iterator<Item = &'static Station> {
+        let (from, to) = (self.order(&self.from), self.order(&self.to));
+        STATIONS.iter().filter(move |station| (from..=to).contains(&station.order))
+    }
+
+    pub fn contains(&self, station: &Station) -> bool {
+        (self.order(&self.from)..=self.order(&self.to)).contains(&station.order)
+    }
+
+    fn order(&self, id: &str) -> u8 {
+        stations::by_id(id).map_or(0, |station| station.order)
+    }
+}
+
+/// Reads the stations out of a location such as `zwischen Ohligsmühle und
+/// Kluse`, `Haltestelle Landgericht` or `Gesamtstrecke`, plus a direction
+/// given as `Richtung Oberbarmen` or `Fahrtrichtung Vohwinkel`. `None` when
+/// no station is named.
+pub fn parse_segment(location: &str) -> Option<Segment> {
+    let words: Vec<&str> = location.split(|c: char| !c.is_alphanumeric() && c != '.').filter(|word| !word.is_empty()).collect();
+
+    let mut named = Vec::new();
+    let mut towards = None;
+    let mut whole_line = false;
+    let mut index = 0;
+    while index < words.len() {
+        let word = stations::normalize(words[index]);
+        if word == "richtung" || word == "fahrtrichtung" {
+            if let Some((station, len)) = station_at(&words[index + 1..]) {
+                towards = Some(station);
+                index += 1 + len;
+                continue;
+            }
+        }
+        if word == "gesamtstrecke" {
+            whole_line = true;
+        }
+        match station_at(&words[index..]) {
+            Some((station, len)) => {
+                if !named.contains(&station) {
+                    named.push(station);
+                }
+                index += len;
+            }
+            None => index += 1,
+        }
+    }
+
+    let (from, to) = match named[..] {
+        [] if whole_line => (STATIONS.first()?, STATIONS.last()?),
+        [] => return None,
+        [station] => (station, station),
+        [a, b, ..] if a.order <= b.order => (a, b),
+        [a, b, ..] => (b, a),
+    };
+    let direction = towards.and_then(|towards| {
+        if towards.order <= from.order && towards.order < to.order {
+            Some(Direction::TowardsVohwinkel)
+        } else if towards.order >= to.order && towards.order > from.order {
+            Some(Direction::TowardsOberbarmen)
+        } else {
+            None
+        }
+    });
+
+    Some(Segment { from: from.id.to_string(), to: to.id.to_string(), direction })
+}
+
+/// The station named by the first one to three of `words`, longest first,
+/// with the number of words it took.
+fn station_at(words: &[&str]) -> Option<(&'static Station, usize)> {
+    (1..=3.min(words.len())).rev().find_map(|len| stations::lookup(&words[..len].join(" ")).map(|station| (station, len)))
+}
@@ -0,0 +1,202 @@
+use chrono::Utc;
+use futures_util::future::BoxFuture;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::archive::Snapshot;
+use crate::config::Config;
+use crate::scrape::{self, ScrapeError};
+use crate::store::Store;
+
+/// Where the pages that are scraped come from. `refresh` asks the source of
+/// `AppState` for one page per scrape and parses it the same way whatever
+/// the source is.
+pub trait DisruptionSource: Send + Sync {
+    fn fetch(&self) -> BoxFuture<'_, Result<Snapshot, ScrapeError>>;
+
+    /// Whether the pages are new and belong in the archive, which only
+    /// holds for the live page.
+    fn archive(&self) -> bool {
+        false
+    }
+}
+
+/// The source selected by `Config`: the live page, or `Config::replay`.
+pub fn from_config(config: &Config) -> Result<Box<dyn DisruptionSource>, String> {
+    let path = match &config.replay {
+        Some(path) => path,
+        None => return Ok(Box::new(LiveSource::new(&config.source_url))),
+    };
+    let source: Box<dyn DisruptionSource> = if is_archive(path) {
+        Box::new(ArchiveSource::open(path)?)
+    } else {
+        Box::new(FileSource::open(path)?)
+    };
+    info!(path = %path.display(), "Replaying pages instead of the live URL");
+    Ok(source)
+}
+
+/// Whether a `replay` path is a database with archived pages rather than
+/// HTML files.
+pub fn is_archive(path: &Path) -> bool {
+    !path.is_dir() && !is_html(path)
+}
+
+fn is_html(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "html" || extension == "htm")
+}
+
+/// The WSW page, fetched over HTTP.
+pub struct LiveSource {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl LiveSource {
+    pub fn new(url: &str) -> LiveSource {
+        LiveSource { client: reqwest::Client::new(), url: url.to_string() }
+    }
+}
+
+impl DisruptionSource for LiveSource {
+    fn fetch(&self) -> BoxFuture<'_, Result<Snapshot, ScrapeError>> {
+        Box::pin(scrape::fetch(&self.client, &self.url))
+    }
+
+    fn archive(&self) -> bool {
+        true
+    }
+}
+
+/// A local HTML file, or the HTML files of a directory in name order. Every
+/// fetch takes the next file; once all were served the last one is repeated.
+pub struct FileSource {
+    files: Vec<PathBuf>,
+    next: AtomicUsize,
+}
+
+impl FileSource {
+    pub fn open(path: &Path) -> Result<FileSource, String> {
+        let files = if path.is_dir() {
+            let mut files: Vec<PathBuf> = std::fs::read_dir(path)
+                .map_err(|e| format!("{}: {}", path.display(), e))?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|file| is_html(file))
+                .collect();
+            files.sort();
+            files
+        } else {
+            vec![path.to_path_buf()]
+        };
+        if files.is_empty() {
+            return Err(format!("{}: no .html files", path.display()));
+        }
+        Ok(FileSource { files, next: AtomicUsize::new(0) })
+    }
+}
+
+impl DisruptionSource for FileSource {
+    fn fetch(&self) -> BoxFuture<'_, Result<Snapshot, ScrapeError>> {
+        let index = self.next.fetch_add(1, Ordering::SeqCst).min(self.files.len() - 1);
+        let file = &self.files[index];
+        Box::pin(async move {
+            let body = tokio::fs::read_to_string(file).await.map_err(|e| ScrapeError::Source(format!("{}: {}", file.display(), e)))?;
+            info!(file = %file.display(), "Read page from file");
+            Ok(page(&file.display().to_string(), 200, body))
+        })
+    }
+}
+
+/// The pages archived in another database, in fetch order, repeating the
+/// last one once all were served.
+pub struct ArchiveSource {
+    store: Arc<Store>,
+    fetches: Vec<i64>,
+    next: AtomicUsize,
+}
+
+impl ArchiveSource {
+    pub fn open(path: &Path) -> Result<ArchiveSource, String> {
+        let store = Store::open_read_only(&path.to_string_lossy()).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let fetches = store.archived_fetches().map_err(|e| format!("{}: {}", path.display(), e))?;
+        if fetches.is_empty() {
+            return Err(format!("{}: no archived pages", path.display()));
+        }
+        Ok(ArchiveSource { store: Arc::new(store), fetches, next: AtomicUsize::new(0) })
+    }
+}
+
+impl DisruptionSource for ArchiveSource {
+    fn fetch(&self) -> BoxFuture<'_, Result<Snapshot, ScrapeError>> {
+        let index = self.next.fetch_add(1, Ordering::SeqCst).min(self.fetches.len() - 1);
+        let id = self.fetches[index];
+        info!(fetch = id, "Replaying archived page");
+        let store = Arc::clone(&self.store);
+        Box::pin(async move {
+            // Reading and decompressing the page blocks, so keep it off the runtime's workers.
+            let snapshot = tokio::task::spawn_blocking(move || store.archived_snapshot(id).map_err(|e| e.to_string()))
+                .await
+                .map_err(|e| ScrapeError::Source(format!("fetch {}: {}", id, e)))?;
+            snapshot.map_err(|e| ScrapeError::Source(format!("fetch {}: {}", id, e)))
+        })
+    }
+}
+
+/// The same in-memory page on every fetch.
+pub struct FixtureSource {
+    body: String,
+}
+
+impl FixtureSource {
+    pub fn new(body: impl Into<String>) -> FixtureSource {
+        FixtureSource { body: body.into() }
+    }
+}
+
+impl DisruptionSource for FixtureSource {
+    fn fetch(&self) -> BoxFuture<'_, Result<Snapshot, ScrapeError>> {
+        let snapshot = page("fixture", 200, self.body.clone());
+        Box::pin(async move { Ok(snapshot) })
+    }
+}
+
+/// One step of a `ScriptedSource`.
+#[derive(Clone, Debug)]
+pub enum Step {
+    /// A response with this HTTP status and body.
+    Page { status: u16, body: String },
+    /// The fetch fails without a response, like an unreachable upstream.
+    Fail(String),
+}
+
+/// Plays a fixed sequence of pages and failures, one step per fetch, then
+/// repeats the last step.
+pub struct ScriptedSource {
+    steps: Vec<Step>,
+    next: AtomicUsize,
+}
+
+impl ScriptedSource {
+    /// Panics on an empty script, which is a bug in the caller.
+    pub fn new(steps: Vec<Step>) -> ScriptedSource {
+        assert!(!steps.is_empty(), "a scripted source needs at least one step");
+        ScriptedSource { steps, next: AtomicUsize::new(0) }
+    }
+}
+
+impl DisruptionSource for ScriptedSource {
+    fn fetch(&self) -> BoxFuture<'_, Result<Snapshot, ScrapeError>> {
+        let index = self.next.fetch_add(1, Ordering::SeqCst).min(self.steps.len() - 1);
+        let result = match &self.steps[index] {
+            Step::Page { status, body } => Ok(page(&format!("script:{}", index), *status, body.clone())),
+            Step::Fail(reason) => Err(ScrapeError::Source(reason.clone())),
+        };
+        Box::pin(async move { result })
+    }
+}
+
+fn page(url: &str, status: u16, body: String) -> Snapshot {
+    Snapshot { fetched_at: Utc::now(), url: url.to_string(), status, content_type: None, duration_ms: 0, body }
+}
@@ -0,0 +1,114 @@
+use actix_web::web::Bytes;
+use actix_web::{web, HttpRequest, HttpResponse};
+use futures_util::stream;
+use serde::Serialize;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tracing::error;
+
+use crate::{current_status, AppState};
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// `/status/stream`: sends the current `Status` as a `status` event, then for
+/// every scrape that changes something one `change` event per change followed
+/// by the new `status`. Event ids are change feed cursors, so a reconnecting
+/// client sending `Last-Event-ID` gets the changes it missed replayed instead
+/// of only the latest snapshot.
+pub async fn status_stream(req: HttpRequest, data: web::Data<Arc<AppState>>) -> HttpResponse {
+    let last_event_id = req
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<i64>().ok());
+
+    // Subscribe before reading the current state so no update falls in between.
+    let mut updates = data.updates.subscribe();
+    let (tx, rx) = mpsc::channel::<Bytes>(16);
+    let state = Arc::clone(&data);
+
+    tokio::spawn(async move {
+        let mut cursor = match send_initial(&state, &tx, last_event_id).await {
+            Some(cursor) => cursor,
+            None => return,
+        };
+
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+        heartbeat.tick().await;
+
+        loop {
+            tokio::select! {
+                update = updates.recv() => match update {
+                    Ok(update) => {
+                        if update.cursor <= cursor {
+                            continue;
+                        }
+                        for change in update.changes.iter().filter(|change| change.cursor > cursor) {
+                            if tx.send(event("change", change.cursor, change)).await.is_err() {
+                                return;
+                            }
+                        }
+                        if tx.send(event("status", update.cursor, &update.status)).await.is_err() {
+                            return;
+                        }
+                        cursor = update.cursor;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => {
+                        // Fell behind the channel; catch up from the store like a resume.
+                        cursor = match send_initial(&state, &tx, Some(cursor)).await {
+                            Some(cursor) => cursor,
+                            None => return,
+                        };
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                },
+                _ = heartbeat.tick() => {
+                    if tx.send(Bytes::from_static(b": heartbeat\n\n")).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    });
+
+    let body = stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|bytes| (Ok::<_, Infallible>(bytes), rx))
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(body)
+}
+
+/// Sends the changes after `last_event_id` (if any) and the current status,
+/// returning the cursor the client is now at, or `None` once it disconnected.
+async fn send_initial(state: &AppState, tx: &mpsc::Sender<Bytes>, last_event_id: Option<i64>) -> Option<i64> {
+    // The cursor that goes with the status, not the store's latest: a scrape
+    // may have recorded changes without having published its status yet.
+    let status = current_status(state);
+    let cursor = status.cursor;
+
+    if let Some(last_event_id) = last_event_id {
+        let missed = match state.store.changes_since(last_event_id, u32::MAX) {
+            Ok(changes) => changes,
+            Err(e) => {
+                error!(error = %e, "Error loading changes");
+                Vec::new()
+            }
+        };
+        for change in missed.iter().filter(|change| change.cursor <= cursor) {
+            tx.send(event("change", change.cursor, change)).await.ok()?;
+        }
+    }
+
+    tx.send(event("status", cursor, &status)).await.ok()?;
+    Some(cursor)
+}
+
+fn event<T: Serialize>(name: &str, id: i64, data: &T) -> Bytes {
+    let data = serde_json::to_string(data).unwrap_or_default();
+    Bytes::from(format!("event: {}\nid: {}\ndata: {}\n\n", name, id, data))
+}
@@ -0,0 +1,123 @@
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::error;
+
+use crate::lifecycle::TrackedDisruption;
+use crate::stations::{self, normalize, Elevator, Station, STATIONS};
+use crate::{current_status, AppState, ElevatorStatus, SchwebebahnDisruption, Status};
+
+/// How far back `recent` goes.
+const HISTORY_DAYS: i64 = 7;
+
+/// One station with what currently affects it.
+#[derive(Serialize, Debug)]
+pub struct StationStatus {
+    station: &'static Station,
+    /// Subway disruptions affecting the station.
+    subway_disruptions: Vec<SchwebebahnDisruption>,
+    /// Listed elevator outages, including ones that haven't started yet.
+    elevator_outages: Vec<ElevatorStatus>,
+    /// The station's elevators and whether each works right now.
+    elevators: Vec<ElevatorState>,
+    /// No elevator outage in effect right now; `None` while the data is
+    /// stale or the parser is broken, as the answer would be a guess.
+    step_free: Option<bool>,
+    /// Disruptions at the station resolved within the last `HISTORY_DAYS`.
+    recent: Vec<TrackedDisruption>,
+}
+
+#[derive(Serialize, Debug)]
+struct ElevatorState {
+    #[serde(flatten)]
+    elevator: &'static Elevator,
+    in_service: bool,
+}
+
+/// `/stations`: every station in line order.
+pub async fn stations(data: web::Data<Arc<AppState>>) -> HttpResponse {
+    let recent = match recently_resolved(&data) {
+        Ok(recent) => recent,
+        Err(e) => {
+            error!(error = %e, "Error loading disruption history");
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let status = current_status(&data);
+    let now = Utc::now();
+
+    let stations: Vec<StationStatus> = STATIONS.iter().map(|station| station_status(station, &status, &recent, now)).collect();
+    HttpResponse::Ok().json(stations)
+}
+
+/// `/stations/{id}`, with `id` as in the registry.
+pub async fn station(data: web::Data<Arc<AppState>>, id: web::Path<String>) -> HttpResponse {
+    let station = match stations::by_id(&id) {
+        Some(station) => station,
+        None => return HttpResponse::NotFound().finish(),
+    };
+    let recent = match recently_resolved(&data) {
+        Ok(recent) => recent,
+        Err(e) => {
+            error!(error = %e, "Error loading disruption history");
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let status = current_status(&data);
+
+    HttpResponse::Ok().json(station_status(station, &status, &recent, Utc::now()))
+}
+
+fn recently_resolved(state: &AppState) -> Result<Vec<TrackedDisruption>, Box<dyn std::error::Error>> {
+    state.store.resolved_since(Utc::now() - Duration::days(HISTORY_DAYS))
+}
+
+fn station_status(station: &'static Station, status: &Status, recent: &[TrackedDisruption], now: DateTime<Utc>) -> StationStatus {
+    let subway_disruptions: Vec<SchwebebahnDisruption> = status
+        .schwebebahn_disruptions
+        .iter()
+        .filter(|disruption| disruption.affects(station))
+        .cloned()
+        .collect();
+    let elevator_outages: Vec<ElevatorStatus> = status
+        .elevators
+        .iter()
+        .filter(|elevator| elevator.station_id.as_deref() == Some(station.id))
+        .cloned()
+        .collect();
+
+    let in_effect: Vec<&ElevatorStatus> = elevator_outages
+        .iter()
+        .filter(|outage| in_effect(outage.start_time, outage.end_time, now))
+        .collect();
+    let elevators = station
+        .elevators
+        .iter()
+        .map(|elevator| ElevatorState {
+            elevator,
+            in_service: !in_effect.iter().any(|outage| serves(elevator, &outage.location)),
+        })
+        .collect();
+    // Any outage in effect counts, including one that matches none of the
+    // elevators we know of.
+    let known = !status.health.stale && !status.health.parser_broken && status.last_updated.is_some();
+    let step_free = known.then_some(in_effect.is_empty());
+
+    let recent = recent
+        .iter()
+        .filter(|tracked| tracked.disruption.mentions_station(station.id))
+        .cloned()
+        .collect();
+
+    StationStatus { station, subway_disruptions, elevator_outages, elevators, step_free, recent }
+}
+
+fn in_effect(start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+    start.is_none_or(|start| start <= now) && end.is_none_or(|end| end > now)
+}
+
+/// Whether an outage's location text refers to `elevator`.
+fn serves(elevator: &Elevator, location: &str) -> bool {
+    normalize(location).contains(&normalize(elevator.location))
+}
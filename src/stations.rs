@@ -0,0 +1,271 @@
+use serde::Serialize;
+
+/// One of the Schwebebahn's stations. `id` is the key every per-station
+/// feature uses; names as scraped are mapped to it by `lookup`.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Station {
+    pub id: &'static str,
+    pub name: &'static str,
+    /// Position on the line, 1 for Vohwinkel up to 20 for Oberbarmen.
+    pub order: u8,
+    /// Approximate, good enough to place the station on a map.
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Other spellings WSW or people use, on top of what `normalize` covers.
+    pub aliases: &'static [&'static str],
+    pub elevators: &'static [Elevator],
+}
+
+/// An elevator of a station, identified by the platform or entrance it
+/// serves, which is what `ElevatorStatus.location` names.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Elevator {
+    pub id: &'static str,
+    pub location: &'static str,
+}
+
+macro_rules! platforms {
+    ($station:literal) => {
+        &[
+            Elevator { id: concat!($station, "-richtung-vohwinkel"), location: "Bahnsteig Richtung Vohwinkel" },
+            Elevator { id: concat!($station, "-richtung-oberbarmen"), location: "Bahnsteig Richtung Oberbarmen" },
+        ]
+    };
+}
+
+/// All stations from Vohwinkel to Oberbarmen, in line order.
+pub static STATIONS: [Station; 20] = [
+    Station {
+        id: "vohwinkel",
+        name: "Vohwinkel",
+        order: 1,
+        latitude: 51.2318,
+        longitude: 7.0734,
+        aliases: &["Vohwinkel Schwebebahn"],
+        elevators: &[Elevator { id: "vohwinkel-bahnsteig", location: "Bahnsteig" }],
+    },
+    Station {
+        id: "bruch",
+        name: "Bruch",
+        order: 2,
+        latitude: 51.2330,
+        longitude: 7.0818,
+        aliases: &[],
+        elevators: platforms!("bruch"),
+    },
+    Station {
+        id: "hammerstein",
+        name: "Hammerstein",
+        order: 3,
+        latitude: 51.2370,
+        longitude: 7.0932,
+        aliases: &[],
+        elevators: platforms!("hammerstein"),
+    },
+    Station {
+        id: "sonnborner-strasse",
+        name: "Sonnborner Straße",
+        order: 4,
+        latitude: 51.2380,
+        longitude: 7.1040,
+        aliases: &["Sonnborn"],
+        elevators: platforms!("sonnborner-strasse"),
+    },
+    Station {
+        id: "zoo-stadion",
+        name: "Zoo/Stadion",
+        order: 5,
+        latitude: 51.2395,
+        longitude: 7.1116,
+        aliases: &["Zoo", "Stadion", "Zoologischer Garten"],
+        elevators: platforms!("zoo-stadion"),
+    },
+    Station {
+        id: "varresbecker-strasse",
+        name: "Varresbecker Straße",
+        order: 6,
+        latitude: 51.2430,
+        longitude: 7.1190,
+        aliases: &["Varresbeck"],
+        elevators: platforms!("varresbecker-strasse"),
+    },
+    Station {
+        id: "westende",
+        name: "Westende",
+        order: 7,
+        latitude: 51.2452,
+        longitude: 7.1263,
+        aliases: &[],
+        elevators: platforms!("westende"),
+    },
+    Station {
+        id: "pestalozzistrasse",
+        name: "Pestalozzistraße",
+        order: 8,
+        latitude: 51.2470,
+        longitude: 7.1317,
+        aliases: &[],
+        elevators: platforms!("pestalozzistrasse"),
+    },
+    Station {
+        id: "robert-daum-platz",
+        name: "Robert-Daum-Platz",
+        order: 9,
+        latitude: 51.2495,
+        longitude: 7.1398,
+        aliases: &[],
+        elevators: platforms!("robert-daum-platz"),
+    },
+    Station {
+        id: "ohligsmuehle",
+        name: "Ohligsmühle",
+        order: 10,
+        latitude: 51.2527,
+        longitude: 7.1448,
+        aliases: &[],
+        elevators: platforms!("ohligsmuehle"),
+    },
+    Station {
+        id: "hauptbahnhof",
+        name: "Hauptbahnhof",
+        order: 11,
+        latitude: 51.2544,
+        longitude: 7.1498,
+        aliases: &["Hbf", "Wuppertal Hbf", "Wuppertal Hauptbahnhof", "Döppersberg", "Hauptbahnhof/Döppersberg"],
+        elevators: platforms!("hauptbahnhof"),
+    },
+    Station {
+        id: "kluse",
+        name: "Kluse",
+        order: 12,
+        latitude: 51.2566,
+        longitude: 7.1546,
+        aliases: &["Kluse/Schauspielhaus"],
+        elevators: platforms!("kluse"),
+    },
+    Station {
+        id: "landgericht",
+        name: "Landgericht",
+        order: 13,
+        latitude: 51.2591,
+        longitude: 7.1605,
+        aliases: &[],
+        elevators: platforms!("landgericht"),
+    },
+    Station {
+        id: "voelklinger-strasse",
+        name: "Völklinger Straße",
+        order: 14,
+        latitude: 51.2620,
+        longitude: 7.1692,
+        aliases: &[],
+        elevators: platforms!("voelklinger-strasse"),
+    },
+    Station {
+        id: "loher-bruecke",
+        name: "Loher Brücke",
+        order: 15,
+        latitude: 51.2643,
+        longitude: 7.1793,
+        aliases: &[],
+        elevators: platforms!("loher-bruecke"),
+    },
+    Station {
+        id: "adlerbruecke",
+        name: "Adlerbrücke",
+        order: 16,
+        latitude: 51.2663,
+        longitude: 7.1880,
+        aliases: &[],
+        elevators: platforms!("adlerbruecke"),
+    },
+    Station {
+        id: "alter-markt",
+        name: "Alter Markt",
+        order: 17,
+        latitude: 51.2699,
+        longitude: 7.1962,
+        aliases: &["Barmen Alter Markt"],
+        elevators: platforms!("alter-markt"),
+    },
+    Station {
+        id: "werther-bruecke",
+        name: "Werther Brücke",
+        order: 18,
+        latitude: 51.2713,
+        longitude: 7.2061,
+        aliases: &[],
+        elevators: platforms!("werther-bruecke"),
+    },
+    Station {
+        id: "wupperfeld",
+        name: "Wupperfeld",
+        order: 19,
+        latitude: 51.2721,
+        longitude: 7.2147,
+        aliases: &[],
+        elevators: platforms!("wupperfeld"),
+    },
+    Station {
+        id: "oberbarmen",
+        name: "Oberbarmen",
+        order: 20,
+        latitude: 51.2735,
+        longitude: 7.2229,
+        aliases: &["Oberbarmen Bf", "Bahnhof Oberbarmen", "Berliner Platz"],
+        elevators: &[Elevator { id: "oberbarmen-bahnsteig", location: "Bahnsteig" }],
+    },
+];
+
+pub fn by_id(id: &str) -> Option<&'static Station> {
+    STATIONS.iter().find(|station| station.id == id)
+}
+
+/// The station a scraped name refers to: its id, name or one of its aliases,
+/// compared after `normalize`.
+pub fn lookup(name: &str) -> Option<&'static Station> {
+    let name = normalize(name);
+    if name.is_empty() {
+        return None;
+    }
+    STATIONS.iter().find(|station| station.names().any(|known| normalize(known) == name))
+}
+
+impl Station {
+    /// The name followed by the aliases.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> {
+        std::iter::once(self.name).chain(self.aliases.iter().copied())
+    }
+
+    /// Whether free text such as a subway row's location names this station.
+    /// Whole words only, so `Rohrbruch` doesn't mention Bruch; up to three
+    /// words are joined, so `Wertherbrücke` matches `Werther Brücke`.
+    pub fn is_mentioned_in(&self, text: &str) -> bool {
+        let words: Vec<String> = text
+            .split(|c: char| !c.is_alphanumeric() && c != '.')
+            .map(normalize)
+            .filter(|word| !word.is_empty())
+            .collect();
+        self.names().map(normalize).any(|name| {
+            (1..=3).any(|len| words.windows(len).any(|window| window.concat() == name))
+        })
+    }
+}
+
+/// Folds the spelling differences seen in practice: case, umlauts and ß,
+/// `Str.` for `Straße`, and spaces, hyphens and slashes, so
+/// `Sonnborner Str.` and `sonnborner-strasse` both become `sonnbornerstrasse`.
+pub fn normalize(name: &str) -> String {
+    let mut folded = String::new();
+    for c in name.trim().to_lowercase().chars() {
+        match c {
+            'ä' => folded.push_str("ae"),
+            'ö' => folded.push_str("oe"),
+            'ü' => folded.push_str("ue"),
+            'ß' => folded.push_str("ss"),
+            c if c.is_alphanumeric() || c == '.' => folded.push(c),
+            _ => {}
+        }
+    }
+    folded.replace("str.", "strasse").replace('.', "")
+}
@@ -0,0 +1,467 @@
+use chrono::{DateTime, TimeZone, Utc};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OpenFlags, OptionalExtension, Params, Transaction};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use crate::archive::{self, Snapshot};
+use crate::changes::{Change, ChangeKind};
+use crate::history::{HistoryQuery, Page, ScrapeRecord};
+use crate::lifecycle::{Disruption, TrackedDisruption};
+use crate::Status;
+
+/// Local SQLite file holding every scrape result, so a restart doesn't lose
+/// what we knew and the history can be queried later.
+pub struct Store {
+    conn: Mutex<Connection>,
+}
+
+impl Store {
+    pub fn open(path: &str) -> Result<Store, Box<dyn std::error::Error>> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS scrapes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                scraped_at INTEGER NOT NULL,
+                status TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS scrapes_scraped_at ON scrapes (scraped_at);
+            CREATE TABLE IF NOT EXISTS disruptions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                key TEXT NOT NULL,
+                transportation TEXT NOT NULL,
+                station TEXT NOT NULL,
+                event TEXT NOT NULL,
+                disruption TEXT NOT NULL,
+                first_seen INTEGER NOT NULL,
+                last_seen INTEGER NOT NULL,
+                resolved_at INTEGER
+            );
+            CREATE INDEX IF NOT EXISTS disruptions_active ON disruptions (key) WHERE resolved_at IS NULL;
+            CREATE TABLE IF NOT EXISTS changes (
+                cursor INTEGER PRIMARY KEY AUTOINCREMENT,
+                at INTEGER NOT NULL,
+                kind TEXT NOT NULL,
+                key TEXT NOT NULL,
+                disruption TEXT NOT NULL,
+                previous TEXT
+            );
+            CREATE TABLE IF NOT EXISTS webhook_dead_letters (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                failed_at INTEGER NOT NULL,
+                url TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                attempts INTEGER NOT NULL,
+                last_error TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS pages (
+                hash TEXT PRIMARY KEY,
+                size INTEGER NOT NULL,
+                body BLOB NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS fetches (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                fetched_at INTEGER NOT NULL,
+                url TEXT NOT NULL,
+                status INTEGER NOT NULL,
+                content_type TEXT,
+                duration_ms INTEGER NOT NULL,
+                hash TEXT NOT NULL REFERENCES pages (hash)
+            );",
+        )?;
+        Ok(Store { conn: Mutex::new(conn) })
+    }
+
+    /// Opens another instance's database without creating tables or writing
+    /// anything, for reading its page archive.
+    pub fn open_read_only(path: &str) -> Result<Store, Box<dyn std::error::Error>> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        Ok(Store { conn: Mutex::new(conn) })
+    }
+
+    pub fn record_scrape(&self, scraped_at: DateTime<Utc>, status: &Status) -> Result<(), Box<dyn std::error::Error>> {
+        let json = serde_json::to_string(status)?;
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO scrapes (scraped_at, status) VALUES (?1, ?2)",
+            params![scraped_at.timestamp(), json],
+        )?;
+        Ok(())
+    }
+
+    /// The most recently recorded `Status`, used to seed `AppState` on startup.
+    pub fn latest_status(&self) -> Result<Option<Status>, Box<dyn std::error::Error>> {
+        let conn = self.conn.lock().unwrap();
+        let json: Option<String> = conn
+            .query_row("SELECT status FROM scrapes ORDER BY id DESC LIMIT 1", [], |row| row.get(0))
+            .optional()?;
+        match json {
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Rows on the page for each scrape recorded since `since`, as kept in
+    /// `ScrapeHealth::rows`. Scrapes recorded before that field existed are skipped.
+    pub fn row_counts_since(&self, since: DateTime<Utc>) -> Result<Vec<u64>, Box<dyn std::error::Error>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(
+            "SELECT json_extract(status, '$.health.rows') AS rows FROM scrapes
+             WHERE scraped_at >= ?1 AND rows IS NOT NULL",
+        )?;
+        let rows = statement.query_map(params![since.timestamp()], |row| row.get::<_, i64>(0))?;
+
+        let mut counts = Vec::new();
+        for row in rows {
+            counts.push(row? as u64);
+        }
+        Ok(counts)
+    }
+
+    /// Records a fetch. The page itself is stored gzipped, once per distinct
+    /// body, so an unchanged page only costs a `fetches` row.
+    pub fn archive_snapshot(&self, snapshot: &Snapshot) -> Result<(), Box<dyn std::error::Error>> {
+        let hash = snapshot.hash();
+        let conn = self.conn.lock().unwrap();
+        let known: bool = conn.query_row("SELECT EXISTS (SELECT 1 FROM pages WHERE hash = ?1)", params![hash], |row| row.get(0))?;
+        if !known {
+            conn.execute(
+                "INSERT INTO pages (hash, size, body) VALUES (?1, ?2, ?3)",
+                params![hash, snapshot.body.len() as i64, archive::compress(&snapshot.body)?],
+            )?;
+        }
+        conn.execute(
+            "INSERT INTO fetches (fetched_at, url, status, content_type, duration_ms, hash) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                snapshot.fetched_at.timestamp(),
+                snapshot.url,
+                snapshot.status,
+                snapshot.content_type,
+                snapshot.duration_ms as i64,
+                hash,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Ids of all archived fetches, oldest first.
+    pub fn archived_fetches(&self) -> Result<Vec<i64>, Box<dyn std::error::Error>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare("SELECT id FROM fetches ORDER BY id")?;
+        let ids = statement.query_map([], |row| row.get(0))?.collect::<Result<_, _>>()?;
+        Ok(ids)
+    }
+
+    pub fn archived_snapshot(&self, id: i64) -> Result<Snapshot, Box<dyn std::error::Error>> {
+        let conn = self.conn.lock().unwrap();
+        let (fetched_at, url, status, content_type, duration_ms, body) = conn.query_row(
+            "SELECT fetched_at, url, status, content_type, duration_ms, body
+             FROM fetches JOIN pages USING (hash) WHERE id = ?1",
+            params![id],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, u16>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, i64>(4)?,
+                    row.get::<_, Vec<u8>>(5)?,
+                ))
+            },
+        )?;
+        Ok(Snapshot {
+            fetched_at: timestamp(fetched_at),
+            url,
+            status,
+            content_type,
+            duration_ms: duration_ms as u64,
+            body: archive::decompress(&body)?,
+        })
+    }
+
+    /// Matches the disruptions of one scrape against the active ones by
+    /// `Disruption::key`: known ones get `last_seen` bumped, new ones start an
+    /// entry and active ones that are missing are marked resolved. Every
+    /// addition, removal and content change is appended to the change feed
+    /// and returned.
+    pub fn track(&self, seen_at: DateTime<Utc>, disruptions: &[Disruption]) -> Result<Vec<Change>, Box<dyn std::error::Error>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let mut active = HashMap::new();
+        {
+            let mut statement = tx.prepare("SELECT key, id, disruption FROM disruptions WHERE resolved_at IS NULL")?;
+            let rows = statement.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, (row.get::<_, i64>(1)?, row.get::<_, String>(2)?)))
+            })?;
+            for row in rows {
+                let (key, entry) = row?;
+                active.insert(key, entry);
+            }
+        }
+
+        let mut changes = Vec::new();
+        let mut seen = HashSet::new();
+        for disruption in disruptions {
+            let key = disruption.key();
+            if !seen.insert(key.clone()) {
+                continue;
+            }
+            let json = serde_json::to_string(disruption)?;
+            match active.remove(&key) {
+                Some((id, previous)) => {
+                    tx.execute(
+                        "UPDATE disruptions SET station = ?1, event = ?2, disruption = ?3, last_seen = ?4 WHERE id = ?5",
+                        params![disruption.station(), disruption.event(), json, seen_at.timestamp(), id],
+                    )?;
+                    if previous != json {
+                        let previous = serde_json::from_str(&previous)?;
+                        changes.push(record_change(&tx, seen_at, ChangeKind::Modified, key, disruption.clone(), Some(previous))?);
+                    }
+                }
+                None => {
+                    tx.execute(
+                        "INSERT INTO disruptions (key, transportation, station, event, disruption, first_seen, last_seen)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)",
+                        params![key, disruption.transportation(), disruption.station(), disruption.event(), json, seen_at.timestamp()],
+                    )?;
+                    changes.push(record_change(&tx, seen_at, ChangeKind::Added, key, disruption.clone(), None)?);
+                }
+            }
+        }
+
+        let mut resolved: Vec<_> = active.into_iter().collect();
+        resolved.sort_by_key(|(_, (id, _))| *id);
+        for (key, (id, previous)) in resolved {
+            tx.execute(
+                "UPDATE disruptions SET resolved_at = ?1 WHERE id = ?2",
+                params![seen_at.timestamp(), id],
+            )?;
+            let previous = serde_json::from_str(&previous)?;
+            changes.push(record_change(&tx, seen_at, ChangeKind::Removed, key, previous, None)?);
+        }
+
+        tx.commit()?;
+        Ok(changes)
+    }
+
+    /// Up to `limit` changes with a cursor greater than `since`, oldest first.
+    pub fn changes_since(&self, since: i64, limit: u32) -> Result<Vec<Change>, Box<dyn std::error::Error>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(
+            "SELECT cursor, at, kind, key, disruption, previous FROM changes
+             WHERE cursor > ?1 ORDER BY cursor LIMIT ?2",
+        )?;
+        let rows = statement.query_map(params![since, limit], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, Option<String>>(5)?,
+            ))
+        })?;
+
+        let mut changes = Vec::new();
+        for row in rows {
+            let (cursor, at, kind, key, disruption, previous) = row?;
+            changes.push(Change {
+                cursor,
+                at: timestamp(at),
+                kind: kind.parse()?,
+                key,
+                disruption: serde_json::from_str(&disruption)?,
+                previous: previous.map(|previous| serde_json::from_str(&previous)).transpose()?,
+            });
+        }
+        Ok(changes)
+    }
+
+    /// The cursor of the newest change, or 0 if nothing has changed yet.
+    pub fn latest_cursor(&self) -> Result<i64, Box<dyn std::error::Error>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(conn.query_row("SELECT COALESCE(MAX(cursor), 0) FROM changes", [], |row| row.get(0))?)
+    }
+
+    /// Keeps a webhook payload that could not be delivered so it can be
+    /// inspected and replayed by hand.
+    pub fn record_dead_letter(&self, failed_at: DateTime<Utc>, url: &str, payload: &str, attempts: u32, last_error: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO webhook_dead_letters (failed_at, url, payload, attempts, last_error) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![failed_at.timestamp(), url, payload, attempts, last_error],
+        )?;
+        Ok(())
+    }
+
+    pub fn active_disruptions(&self) -> Result<Vec<TrackedDisruption>, Box<dyn std::error::Error>> {
+        self.query_tracked(
+            "SELECT key, disruption, first_seen, last_seen, resolved_at FROM disruptions
+             WHERE resolved_at IS NULL ORDER BY first_seen, id",
+            [],
+        )
+    }
+
+    /// Disruptions that ended at or after `since`, most recently resolved first.
+    pub fn resolved_since(&self, since: DateTime<Utc>) -> Result<Vec<TrackedDisruption>, Box<dyn std::error::Error>> {
+        self.query_tracked(
+            "SELECT key, disruption, first_seen, last_seen, resolved_at FROM disruptions
+             WHERE resolved_at >= ?1 ORDER BY resolved_at DESC, id DESC",
+            params![since.timestamp()],
+        )
+    }
+
+    /// Disruption entries that were active at some point in the query's time
+    /// range and match its filters, oldest first.
+    pub fn disruption_history(&self, query: &HistoryQuery) -> Result<Page<TrackedDisruption>, Box<dyn std::error::Error>> {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        if let Some(from) = query.from() {
+            conditions.push("(resolved_at IS NULL OR resolved_at >= ?)");
+            values.push(Value::Integer(from.timestamp()));
+        }
+        if let Some(to) = query.to() {
+            conditions.push("first_seen < ?");
+            values.push(Value::Integer(to.timestamp()));
+        }
+        if let Some(transportation) = &query.transportation {
+            conditions.push("transportation = ?");
+            values.push(Value::Text(transportation.clone()));
+        }
+        if let Some(event) = &query.event {
+            conditions.push("lower(event) = lower(?)");
+            values.push(Value::Text(event.clone()));
+        }
+        let filter = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+
+        // Subway rows have no station column to match, and SQLite only folds
+        // ASCII case, so the station filter runs here, the same way as for
+        // webhooks and `/stations`.
+        if let Some(station) = &query.station {
+            let matching: Vec<TrackedDisruption> = self
+                .query_tracked(
+                    &format!(
+                        "SELECT key, disruption, first_seen, last_seen, resolved_at FROM disruptions {}
+                         ORDER BY first_seen, id",
+                        filter
+                    ),
+                    params_from_iter(values.iter()),
+                )?
+                .into_iter()
+                .filter(|tracked| tracked.disruption.mentions_station(station))
+                .collect();
+            let total = matching.len() as u64;
+            let items = matching.into_iter().skip(query.offset() as usize).take(query.per_page() as usize).collect();
+            return Ok(Page { page: query.page(), per_page: query.per_page(), total, items });
+        }
+
+        let total: i64 = {
+            let conn = self.conn.lock().unwrap();
+            conn.query_row(
+                &format!("SELECT COUNT(*) FROM disruptions {}", filter),
+                params_from_iter(values.iter()),
+                |row| row.get(0),
+            )?
+        };
+
+        values.push(Value::Integer(query.per_page().into()));
+        values.push(Value::Integer(query.offset().into()));
+        let items = self.query_tracked(
+            &format!(
+                "SELECT key, disruption, first_seen, last_seen, resolved_at FROM disruptions {}
+                 ORDER BY first_seen, id LIMIT ? OFFSET ?",
+                filter
+            ),
+            params_from_iter(values.iter()),
+        )?;
+
+        Ok(Page { page: query.page(), per_page: query.per_page(), total: total as u64, items })
+    }
+
+    /// Recorded scrapes in the query's time range, oldest first. Only `from`,
+    /// `to` and the paging parameters apply.
+    pub fn scrape_history(&self, query: &HistoryQuery) -> Result<Page<ScrapeRecord>, Box<dyn std::error::Error>> {
+        let from = query.from().map_or(i64::MIN, |from| from.timestamp());
+        let to = query.to().map_or(i64::MAX, |to| to.timestamp());
+
+        let conn = self.conn.lock().unwrap();
+        let total: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM scrapes WHERE scraped_at >= ?1 AND scraped_at < ?2",
+            params![from, to],
+            |row| row.get(0),
+        )?;
+
+        let mut statement = conn.prepare(
+            "SELECT scraped_at, status FROM scrapes WHERE scraped_at >= ?1 AND scraped_at < ?2
+             ORDER BY id LIMIT ?3 OFFSET ?4",
+        )?;
+        let rows = statement.query_map(params![from, to, query.per_page(), query.offset()], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?;
+
+        let mut items = Vec::new();
+        for row in rows {
+            let (scraped_at, json) = row?;
+            items.push(ScrapeRecord { scraped_at: timestamp(scraped_at), status: serde_json::from_str(&json)? });
+        }
+
+        Ok(Page { page: query.page(), per_page: query.per_page(), total: total as u64, items })
+    }
+
+    fn query_tracked<P: Params>(&self, sql: &str, params: P) -> Result<Vec<TrackedDisruption>, Box<dyn std::error::Error>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(sql)?;
+        let rows = statement.query_map(params, |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, Option<i64>>(4)?,
+            ))
+        })?;
+
+        let mut tracked = Vec::new();
+        for row in rows {
+            let (key, json, first_seen, last_seen, resolved_at) = row?;
+            tracked.push(TrackedDisruption {
+                key,
+                disruption: serde_json::from_str(&json)?,
+                first_seen: timestamp(first_seen),
+                last_seen: timestamp(last_seen),
+                resolved_at: resolved_at.map(timestamp),
+            });
+        }
+        Ok(tracked)
+    }
+}
+
+fn record_change(
+    tx: &Transaction,
+    at: DateTime<Utc>,
+    kind: ChangeKind,
+    key: String,
+    disruption: Disruption,
+    previous: Option<Disruption>,
+) -> Result<Change, Box<dyn std::error::Error>> {
+    tx.execute(
+        "INSERT INTO changes (at, kind, key, disruption, previous) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            at.timestamp(),
+            kind.as_str(),
+            key,
+            serde_json::to_string(&disruption)?,
+            previous.as_ref().map(serde_json::to_string).transpose()?,
+        ],
+    )?;
+    Ok(Change { cursor: tx.last_insert_rowid(), at, kind, key, disruption, previous })
+}
+
+fn timestamp(seconds: i64) -> DateTime<Utc> {
+    Utc.timestamp_opt(seconds, 0).unwrap()
+}
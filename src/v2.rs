@@ -0,0 +1,50 @@
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::health::ScrapeHealth;
+use crate::line::{Line, LineState};
+use crate::refresh::StatusQuery;
+use crate::{requested_status, AppState, ElevatorStatus, SchwebebahnDisruption};
+
+/// `/v2/status`: the same data as `/status` without the placeholder entries,
+/// so "nothing disrupted" is an empty list and the counts and flags below
+/// instead of magic strings.
+#[derive(Serialize, Debug)]
+pub struct StatusV2 {
+    line: Line,
+    /// False when nothing has been scraped yet, the data is stale or the
+    /// parser is broken, i.e. when `line.state` is `unknown`. The lists then
+    /// hold the last known rows, if any, and the counts and flags are null,
+    /// since an empty list can't be told apart from missing data.
+    data_available: bool,
+    schwebebahn_disruptions: Vec<SchwebebahnDisruption>,
+    /// Elevators out of service.
+    elevators: Vec<ElevatorStatus>,
+    schwebebahn_disruption_count: Option<usize>,
+    elevator_outage_count: Option<usize>,
+    has_schwebebahn_disruptions: Option<bool>,
+    has_elevator_outages: Option<bool>,
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    last_updated: Option<DateTime<Utc>>,
+    health: ScrapeHealth,
+}
+
+pub async fn status(data: web::Data<Arc<AppState>>, query: web::Query<StatusQuery>) -> HttpResponse {
+    let status = requested_status(&data, &query).await;
+    let data_available = status.line.state != LineState::Unknown;
+
+    HttpResponse::Ok().json(StatusV2 {
+        line: status.line,
+        data_available,
+        schwebebahn_disruption_count: data_available.then_some(status.schwebebahn_disruptions.len()),
+        elevator_outage_count: data_available.then_some(status.elevators.len()),
+        has_schwebebahn_disruptions: data_available.then_some(!status.schwebebahn_disruptions.is_empty()),
+        has_elevator_outages: data_available.then_some(!status.elevators.is_empty()),
+        schwebebahn_disruptions: status.schwebebahn_disruptions,
+        elevators: status.elevators,
+        last_updated: status.last_updated,
+        health: status.health,
+    })
+}
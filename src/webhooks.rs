@@ -0,0 +1,162 @@
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{error, warn};
+
+use crate::changes::Change;
+use crate::AppState;
+
+/// Upper bound for `Webhook::max_attempts`, checked by `Config::validate`.
+pub const MAX_ATTEMPTS: u32 = 20;
+/// The delay between attempts stops doubling here.
+const MAX_BACKOFF: Duration = Duration::from_secs(600);
+
+/// A receiver of change notifications. Empty filter lists match everything.
+#[derive(Clone, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+    pub url: String,
+    /// Key for the `X-Signature-256` HMAC-SHA256 of the request body.
+    pub secret: String,
+    /// Station ids or names as in `Disruption::mentions_station`.
+    #[serde(default)]
+    pub stations: Vec<String>,
+    /// Event names as scraped, compared ignoring case.
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// `subway` and/or `elevator`.
+    #[serde(default)]
+    pub transportation: Vec<String>,
+    /// At most `MAX_ATTEMPTS`.
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each further one up to
+    /// `MAX_BACKOFF`.
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+}
+
+fn default_max_attempts() -> u32 {
+    5
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    1000
+}
+
+impl Webhook {
+    fn accepts(&self, change: &Change) -> bool {
+        let disruption = &change.disruption;
+        (self.stations.is_empty() || self.stations.iter().any(|station| disruption.mentions_station(station)))
+            && (self.events.is_empty() || self.events.iter().any(|event| event.eq_ignore_ascii_case(disruption.event())))
+            && (self.transportation.is_empty() || self.transportation.iter().any(|t| t == disruption.transportation()))
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct Payload<'a> {
+    cursor: i64,
+    changes: Vec<&'a Change>,
+}
+
+/// Forwards the changes of every `StatusUpdate` to the webhooks whose filters
+/// match at least one of them. Each delivery runs on its own task so a slow
+/// receiver doesn't hold up the others.
+pub async fn dispatch(state: Arc<AppState>, webhooks: Vec<Webhook>) {
+    if webhooks.is_empty() {
+        return;
+    }
+
+    let client = Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .unwrap_or_default();
+    let mut updates = state.updates.subscribe();
+    let mut cursor = state.status.lock().unwrap().cursor;
+
+    loop {
+        let changes = match updates.recv().await {
+            Ok(update) => update.changes,
+            // Fell behind the channel; fill the gap from the store.
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!(skipped, "Webhook dispatcher fell behind, catching up from the store");
+                match state.store.changes_since(cursor, u32::MAX) {
+                    Ok(changes) => changes,
+                    Err(e) => {
+                        error!(error = %e, "Error loading changes");
+                        continue;
+                    }
+                }
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        let changes: Vec<Change> = changes.into_iter().filter(|change| change.cursor > cursor).collect();
+        let Some(last) = changes.last() else {
+            continue;
+        };
+        cursor = last.cursor;
+
+        for webhook in &webhooks {
+            let changes: Vec<&Change> = changes.iter().filter(|change| webhook.accepts(change)).collect();
+            if changes.is_empty() {
+                continue;
+            }
+            let body = match serde_json::to_string(&Payload { cursor, changes }) {
+                Ok(body) => body,
+                Err(e) => {
+                    error!(error = %e, "Error encoding webhook payload");
+                    continue;
+                }
+            };
+            tokio::spawn(deliver(Arc::clone(&state), client.clone(), webhook.clone(), cursor, body));
+        }
+    }
+}
+
+/// POSTs `body` until the receiver answers with a 2xx, backing off
+/// exponentially between attempts up to `MAX_BACKOFF`. After `max_attempts` the payload goes to
+/// the dead-letter log.
+async fn deliver(state: Arc<AppState>, client: Client, webhook: Webhook, cursor: i64, body: String) {
+    let signature = sign(&webhook.secret, &body);
+    let mut backoff = Duration::from_millis(webhook.initial_backoff_ms).min(MAX_BACKOFF);
+    let mut last_error = String::new();
+
+    for attempt in 1..=webhook.max_attempts.max(1) {
+        let result = client
+            .post(&webhook.url)
+            .header("Content-Type", "application/json")
+            .header("X-Signature-256", format!("sha256={}", signature))
+            .header("X-Change-Cursor", cursor)
+            .body(body.clone())
+            .send()
+            .await;
+
+        match result {
+            Ok(response) if response.status().is_success() => return,
+            Ok(response) => last_error = format!("HTTP {}", response.status()),
+            Err(e) => last_error = e.to_string(),
+        }
+
+        warn!(url = %webhook.url, attempt, error = %last_error, "Webhook delivery failed");
+        if attempt < webhook.max_attempts {
+            tokio::time::sleep(backoff).await;
+            backoff = backoff.saturating_mul(2).min(MAX_BACKOFF);
+        }
+    }
+
+    if let Err(e) = state.store.record_dead_letter(Utc::now(), &webhook.url, &body, webhook.max_attempts, &last_error) {
+        error!(url = %webhook.url, error = %e, "Error recording dead letter");
+    }
+}
+
+/// Hex-encoded HMAC-SHA256 of `body` keyed with `secret`.
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
@@ -0,0 +1,160 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_ws::{Message, Session};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::error;
+
+use crate::changes::Change;
+use crate::lifecycle::Disruption;
+use crate::AppState;
+
+/// Messages a client sends. `id` is echoed in the matching `ack` or `error`.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Subscribe {
+        topics: Vec<String>,
+        id: Option<String>,
+    },
+    Unsubscribe {
+        topics: Vec<String>,
+        id: Option<String>,
+    },
+    Ping {
+        id: Option<String>,
+    },
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage<'a> {
+    /// Confirms a request; `topics` is the full set of subscriptions afterwards.
+    Ack {
+        action: &'a str,
+        id: Option<String>,
+        topics: &'a BTreeSet<String>,
+    },
+    Error {
+        id: Option<String>,
+        message: String,
+    },
+    /// A change matching at least one subscription; `topics` lists which.
+    Change {
+        topics: Vec<&'a str>,
+        change: &'a Change,
+    },
+}
+
+/// `/ws`: a long-lived connection on which clients subscribe to `schwebebahn`,
+/// `elevators` or `station:<id or name>` and receive the matching change feed
+/// entries as they are recorded.
+pub async fn websocket(req: HttpRequest, body: web::Payload, data: web::Data<Arc<AppState>>) -> Result<HttpResponse, actix_web::Error> {
+    let (response, mut session, mut messages) = actix_ws::handle(&req, body)?;
+    let mut updates = data.updates.subscribe();
+    let state = Arc::clone(&data);
+
+    actix_web::rt::spawn(async move {
+        let mut topics = BTreeSet::new();
+        let mut cursor = state.status.lock().unwrap().cursor;
+
+        loop {
+            tokio::select! {
+                message = messages.recv() => match message {
+                    Some(Ok(Message::Text(text))) => {
+                        if handle_client_message(&mut session, &mut topics, &text).await.is_err() {
+                            return;
+                        }
+                    }
+                    Some(Ok(Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            return;
+                        }
+                    }
+                    Some(Ok(Message::Close(reason))) => {
+                        let _ = session.close(reason).await;
+                        return;
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(_)) | None => return,
+                },
+                update = updates.recv() => {
+                    let changes = match update {
+                        Ok(update) => update.changes,
+                        // Fell behind the channel; fill the gap from the store.
+                        Err(broadcast::error::RecvError::Lagged(_)) => match state.store.changes_since(cursor, u32::MAX) {
+                            Ok(changes) => changes,
+                            Err(e) => {
+                                error!(error = %e, "Error loading changes");
+                                continue;
+                            }
+                        },
+                        Err(broadcast::error::RecvError::Closed) => return,
+                    };
+
+                    for change in &changes {
+                        if change.cursor <= cursor {
+                            continue;
+                        }
+                        cursor = change.cursor;
+                        let matching: Vec<&str> = topics.iter().map(String::as_str).filter(|topic| matches(topic, &change.disruption)).collect();
+                        if matching.is_empty() {
+                            continue;
+                        }
+                        if send(&mut session, &ServerMessage::Change { topics: matching, change }).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(response)
+}
+
+async fn handle_client_message(session: &mut Session, topics: &mut BTreeSet<String>, text: &str) -> Result<(), actix_ws::Closed> {
+    let message = match serde_json::from_str::<ClientMessage>(text) {
+        Ok(message) => message,
+        Err(e) => {
+            return send(session, &ServerMessage::Error { id: None, message: format!("invalid message: {}", e) }).await;
+        }
+    };
+
+    match message {
+        ClientMessage::Subscribe { topics: requested, id } => {
+            if let Some(invalid) = requested.iter().find(|topic| !is_valid_topic(topic)) {
+                let message = format!("unknown topic {:?}", invalid);
+                return send(session, &ServerMessage::Error { id, message }).await;
+            }
+            topics.extend(requested);
+            send(session, &ServerMessage::Ack { action: "subscribe", id, topics }).await
+        }
+        ClientMessage::Unsubscribe { topics: requested, id } => {
+            for topic in &requested {
+                topics.remove(topic);
+            }
+            send(session, &ServerMessage::Ack { action: "unsubscribe", id, topics }).await
+        }
+        ClientMessage::Ping { id } => send(session, &ServerMessage::Ack { action: "ping", id, topics }).await,
+    }
+}
+
+async fn send(session: &mut Session, message: &ServerMessage<'_>) -> Result<(), actix_ws::Closed> {
+    session.text(serde_json::to_string(message).unwrap_or_default()).await
+}
+
+fn is_valid_topic(topic: &str) -> bool {
+    match topic.strip_prefix("station:") {
+        Some(station) => !station.trim().is_empty(),
+        None => topic == "schwebebahn" || topic == "elevators",
+    }
+}
+
+fn matches(topic: &str, disruption: &Disruption) -> bool {
+    match (topic, disruption) {
+        ("schwebebahn", Disruption::Subway(_)) | ("elevators", Disruption::Elevator(_)) => true,
+        _ => topic.strip_prefix("station:").is_some_and(|station| disruption.mentions_station(station)),
+    }
+}
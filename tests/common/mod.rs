@@ -0,0 +1,49 @@
+//! Helpers shared by the integration tests; each test binary uses only some
+//! of them.
+#![allow(dead_code)]
+
+use actix_web::{test, web, App};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use schwebebahndisruption::config::Config;
+use schwebebahndisruption::rules::RuleSet;
+use schwebebahndisruption::source::{DisruptionSource, ScriptedSource, Step};
+use schwebebahndisruption::{routes, AppState};
+
+pub fn fixtures() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+/// The contents of `tests/fixtures/<name>`.
+pub fn fixture(name: &str) -> String {
+    fs::read_to_string(fixtures().join(name)).unwrap()
+}
+
+/// A `ScriptedSource` serving the named fixture pages in turn.
+pub fn pages(names: &[&str]) -> ScriptedSource {
+    ScriptedSource::new(names.iter().map(|name| Step::Page { status: 200, body: fixture(name) }).collect())
+}
+
+/// An `AppState` with the built-in rules and otherwise default settings,
+/// recording into an in-memory database.
+pub fn state(source: impl DisruptionSource + 'static) -> Arc<AppState> {
+    state_in(":memory:", source)
+}
+
+/// Like `state`, recording into the database at `database_path`.
+pub fn state_in(database_path: &str, source: impl DisruptionSource + 'static) -> Arc<AppState> {
+    let config = Config { database_path: database_path.to_string(), ..Config::default() };
+    Arc::new(AppState::new(config, RuleSet::load(None).unwrap(), Box::new(source)).unwrap())
+}
+
+/// GETs `uri` from the API around `state`, returning the status code and
+/// the JSON body.
+pub async fn get(state: &Arc<AppState>, uri: &str) -> (u16, Value) {
+    let app = test::init_service(App::new().app_data(web::Data::new(Arc::clone(state))).configure(routes)).await;
+    let response = test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+    let status = response.status().as_u16();
+    (status, test::read_body_json(response).await)
+}
@@ -0,0 +1,24 @@
+use clap::Parser;
+
+use schwebebahndisruption::config::{Cli, Config};
+
+fn load(args: &[&str]) -> Result<Config, String> {
+    Config::load(Cli::parse_from([&["schwebebahndisruption", "--database-path", ":memory:"], args].concat()))
+}
+
+#[test]
+fn minute_settings_are_bounded() {
+    // Beyond a week `chrono::Duration::minutes` would eventually panic at runtime.
+    let error = load(&["--scrape-interval-minutes", "10081", "--activity-window-minutes", "0", "--stale-after-minutes", "99999999999999"]).unwrap_err();
+    assert_eq!(
+        error.lines().collect::<Vec<_>>(),
+        [
+            "scrape_interval_minutes: must be between 1 and 10080",
+            "activity_window_minutes: must be between 1 and 10080",
+            "stale_after_minutes: must be at most 10080",
+        ]
+    );
+
+    let config = load(&["--scrape-interval-minutes", "10080", "--max-age-minutes", "10080", "--stale-after-minutes", "10080"]).unwrap();
+    assert_eq!(config.scrape_interval_minutes, 10080);
+}
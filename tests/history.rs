@@ -0,0 +1,54 @@
+//! `/history` filters, on disruptions tracked from fixture pages.
+
+mod common;
+
+use std::sync::Arc;
+
+use schwebebahndisruption::refresh::refresh;
+use schwebebahndisruption::AppState;
+
+use common::{get, pages, state};
+
+/// The elevators of `station_names.html`, resolved by the next scrape of
+/// `full_closure.html`, whose subway row covers the whole line.
+async fn tracked() -> Arc<AppState> {
+    let state = state(pages(&["station_names.html", "full_closure.html"]));
+    refresh(&state).await;
+    refresh(&state).await;
+    state
+}
+
+async fn history(state: &Arc<AppState>, query: &str) -> (u64, Vec<String>) {
+    let (_, page) = get(state, &format!("/history?{}", query)).await;
+    let ids = page["items"].as_array().unwrap().iter().map(|item| item["disruption"]["id"].as_str().unwrap().to_string()).collect();
+    (page["total"].as_u64().unwrap(), ids)
+}
+
+#[actix_web::test]
+async fn station_filter() {
+    let state = tracked().await;
+
+    for (station, expected) in [
+        // Subway rows count for every station of their segment.
+        ("hauptbahnhof", vec!["ti-8003", "ti-5001"]),
+        ("Wuppertal%20Hbf", vec!["ti-8003", "ti-5001"]),
+        // Scraped as `VÖLKLINGER STRASSE`.
+        ("v%C3%B6lklinger%20stra%C3%9Fe", vec!["ti-8005", "ti-5001"]),
+        ("kluse", vec!["ti-5001", "ti-5002"]),
+        // Not in the registry, so compared by name.
+        ("ronsdorf", vec!["ti-8006"]),
+    ] {
+        let (total, ids) = history(&state, &format!("station={}", station)).await;
+        assert_eq!(ids, expected, "{}", station);
+        assert_eq!(total, expected.len() as u64, "{}", station);
+    }
+}
+
+#[actix_web::test]
+async fn station_filter_pages_and_combines() {
+    let state = tracked().await;
+
+    assert_eq!(history(&state, "station=hauptbahnhof&per_page=1&page=2").await, (2, vec!["ti-5001".to_string()]));
+    assert_eq!(history(&state, "station=hauptbahnhof&transportation=elevator").await, (1, vec!["ti-8003".to_string()]));
+    assert_eq!(history(&state, "per_page=2").await.0, 8);
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use schwebebahndisruption::line::{classify, Line, LineState};
+use schwebebahndisruption::period::Period;
+use schwebebahndisruption::rules::Rules;
+use schwebebahndisruption::scrape::parse_page;
+use schwebebahndisruption::segment::parse_segment;
+use schwebebahndisruption::SchwebebahnDisruption;
+
+use common::fixture;
+
+fn fixture_line(name: &str, now: DateTime<Utc>) -> Line {
+    let scrape = parse_page(&fixture(&format!("{}.html", name)), &Rules::default()).unwrap();
+    classify(&scrape.schwebebahn, now)
+}
+
+fn disruption(event: &str, location: &str, info: &str) -> SchwebebahnDisruption {
+    SchwebebahnDisruption {
+        id: String::new(),
+        event: event.to_string(),
+        location: location.to_string(),
+        segment: parse_segment(location),
+        start_time: None,
+        end_time: None,
+        period: Period::Unknown,
+        raw_period: String::new(),
+        info: info.to_string(),
+    }
+}
+
+#[test]
+fn fixtures() {
+    let line = fixture_line("full_closure", Utc.with_ymd_and_hms(2024, 11, 20, 12, 0, 0).unwrap());
+    assert_eq!(line.state, LineState::Suspended);
+    assert_eq!(line.reason, "Kein Betrieb auf der Gesamtstrecke (Betriebsunterbrechung)");
+
+    // Announced, but not started yet.
+    let line = fixture_line("full_closure", Utc.with_ymd_and_hms(2024, 11, 15, 12, 0, 0).unwrap());
+    assert_eq!(line, Line { state: LineState::Normal, reason: "Regulärer Betrieb".to_string() });
+
+    // Without an id the row's info, which says the station is skipped, can't be found.
+    let line = fixture_line("missing_ids", Utc.with_ymd_and_hms(2024, 6, 15, 12, 0, 0).unwrap());
+    assert_eq!(line, Line { state: LineState::Normal, reason: "Regulärer Betrieb (Bauarbeiten: Haltestelle Landgericht)".to_string() });
+
+    // Reduced frequency only.
+    let line = fixture_line("odd_periods", Utc.with_ymd_and_hms(2024, 5, 6, 10, 0, 0).unwrap());
+    assert_eq!(line.state, LineState::Normal);
+    assert_eq!(line.reason, "Regulärer Betrieb (Bauarbeiten: zwischen Ohligsmühle und Kluse)");
+
+    // The night the clocks go back.
+    let line = fixture_line("odd_periods", Utc.with_ymd_and_hms(2024, 10, 27, 0, 45, 0).unwrap());
+    assert_eq!(line.state, LineState::Suspended);
+}
+
+#[test]
+fn rows() {
+    let now = Utc::now();
+    for (row, state, reason) in [
+        (
+            disruption("Betriebsunterbrechung", "zwischen Zoo/Stadion und Westende", "Die Schwebebahn fährt in diesem Abschnitt nicht."),
+            LineState::PartialService,
+            "Kein Betrieb zwischen Zoo/Stadion und Westende",
+        ),
+        (
+            disruption("Bauarbeiten", "zwischen Vohwinkel und Hammerstein", "Pendelverkehr zwischen Vohwinkel und Hammerstein."),
+            LineState::PartialService,
+            "Pendelverkehr zwischen Vohwinkel und Hammerstein",
+        ),
+        (
+            disruption("Sperrung", "Gesamtstrecke Richtung Oberbarmen", ""),
+            LineState::PartialService,
+            "Kein Betrieb auf der Gesamtstrecke in Richtung Oberbarmen",
+        ),
+        (
+            disruption("Bauarbeiten", "Haltestelle Landgericht", "Die Haltestelle Landgericht wird ohne Halt durchfahren."),
+            LineState::PartialService,
+            "Kein Halt an Landgericht",
+        ),
+        (disruption("Betriebsunterbrechung", "Wuppertal", ""), LineState::PartialService, "Betriebsunterbrechung: Wuppertal"),
+        (disruption("Störung", "Gesamtstrecke", "Verspätungen möglich."), LineState::Normal, "Regulärer Betrieb (Störung: Gesamtstrecke)"),
+    ] {
+        assert_eq!(classify(&[row], now), Line { state, reason: reason.to_string() });
+    }
+
+    // The worst row decides.
+    let rows = [
+        disruption("Bauarbeiten", "Haltestelle Kluse", "Die Haltestelle Kluse entfällt."),
+        disruption("Betriebsunterbrechung", "Gesamtstrecke", ""),
+    ];
+    assert_eq!(classify(&rows, now).state, LineState::Suspended);
+    assert_eq!(classify(&[], now), Line { state: LineState::Normal, reason: "Regulärer Betrieb".to_string() });
+}
@@ -0,0 +1,133 @@
+//! Golden tests for the page parser: every `tests/fixtures/*.html` page is
+//! parsed with the built-in rules and the resulting `Status` compared with
+//! the `.json` file next to it. Run with `UPDATE_GOLDEN=1` to rewrite the
+//! golden files after an intended change, then review the diff.
+
+mod common;
+
+use chrono::{DateTime, TimeZone, Utc};
+use std::fs;
+
+use schwebebahndisruption::drift;
+use schwebebahndisruption::rules::Rules;
+use schwebebahndisruption::scrape::parse_page;
+use schwebebahndisruption::status_from_scrape;
+use schwebebahndisruption::store::Store;
+
+use common::{fixture, fixtures};
+
+fn check(name: &str) {
+    check_at(name, Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap());
+}
+
+/// `now` is fixed, so the golden files don't depend on when the test runs,
+/// and falls within the fixture's periods, so `line` classifies them.
+fn check_at(name: &str, now: DateTime<Utc>) {
+    let html = fixture(&format!("{}.html", name));
+    let scrape = parse_page(&html, &Rules::default()).unwrap();
+    let actual = serde_json::to_value(status_from_scrape(now, scrape)).unwrap();
+
+    let golden = fixtures().join(format!("{}.json", name));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&golden, serde_json::to_string_pretty(&actual).unwrap() + "\n").unwrap();
+        return;
+    }
+    let expected: serde_json::Value = serde_json::from_str(&fs::read_to_string(&golden).unwrap()).unwrap();
+    assert_eq!(
+        actual,
+        expected,
+        "{} no longer parses to {}; if intended, rerun with UPDATE_GOLDEN=1\nactual: {}",
+        name,
+        golden.display(),
+        serde_json::to_string_pretty(&actual).unwrap()
+    );
+}
+
+#[test]
+fn no_disruptions() {
+    check("no_disruptions");
+}
+
+#[test]
+fn only_elevators() {
+    check("only_elevators");
+}
+
+#[test]
+fn only_buses() {
+    check("only_buses");
+}
+
+#[test]
+fn full_closure() {
+    check_at("full_closure", Utc.with_ymd_and_hms(2024, 11, 20, 12, 0, 0).unwrap());
+}
+
+#[test]
+fn odd_periods() {
+    check("odd_periods");
+}
+
+#[test]
+fn missing_ids() {
+    check_at("missing_ids", Utc.with_ymd_and_hms(2024, 6, 15, 12, 0, 0).unwrap());
+}
+
+#[test]
+fn station_names() {
+    check("station_names");
+}
+
+#[test]
+fn every_fixture_has_a_test() {
+    let tested = ["no_disruptions", "only_elevators", "only_buses", "full_closure", "odd_periods", "missing_ids", "station_names"];
+    for entry in fs::read_dir(fixtures()).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|extension| extension == "html") {
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            assert!(tested.contains(&name.as_str()), "add a test for fixture {}", name);
+        }
+    }
+}
+
+/// Every saved page is a real page, so the drift check must accept all of
+/// them; rejecting one would serve the line as unknown.
+#[test]
+fn every_fixture_passes_the_drift_check() {
+    let store = Store::open(":memory:").unwrap();
+    let now = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+    for entry in fs::read_dir(fixtures()).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|extension| extension == "html") {
+            let scrape = parse_page(&fs::read_to_string(&path).unwrap(), &Rules::default()).unwrap();
+            if let Err(e) = drift::check(&scrape, &store, now) {
+                panic!("{} fails the drift check: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+#[test]
+fn rows_without_events_are_a_structure_change() {
+    let row = r#"<tr class="traffic-information-infos" id="ti-1" data-transportation="elevator">
+        <td class="cell-line"><span class="fw-bold">Kluse</span></td><td class="cell-renamed">Aufzugsstörung</td></tr>"#;
+    let html = format!(r#"<html><body><table class="table traffic-information"><tbody>{}</tbody></table></body></html>"#, row.repeat(3));
+    let scrape = parse_page(&html, &Rules::default()).unwrap();
+    let error = drift::check(&scrape, &Store::open(":memory:").unwrap(), Utc::now()).unwrap_err();
+    assert_eq!(error.kind(), "structure_changed");
+}
+
+#[test]
+fn rows_without_transportation_are_a_structure_change() {
+    let row = r#"<tr class="traffic-information-infos" id="ti-1"><td class="cell-line">Kluse</td></tr>"#;
+    let html = format!(r#"<html><body><table class="table traffic-information"><tbody>{}</tbody></table></body></html>"#, row.repeat(2));
+    let scrape = parse_page(&html, &Rules::default()).unwrap();
+    let error = drift::check(&scrape, &Store::open(":memory:").unwrap(), Utc::now()).unwrap_err();
+    assert_eq!(error.to_string(), "page structure changed: 2 of 2 rows have no data-transportation");
+}
+
+#[test]
+fn page_without_table_is_a_structure_change() {
+    let error = parse_page("<html><body><p>Wartungsarbeiten</p></body></html>", &Rules::default()).unwrap_err();
+    assert_eq!(error.kind(), "structure_changed");
+}
@@ -0,0 +1,124 @@
+//! Property tests for `parse_period`, the part of the parser that sees the
+//! most free-form text.
+
+use chrono::{Duration, NaiveDate, NaiveTime, TimeZone};
+use chrono_tz::Europe::Berlin;
+use proptest::prelude::*;
+
+use schwebebahndisruption::period::{berlin_to_utc, parse_period, Moment, Period};
+
+fn date() -> impl Strategy<Value = NaiveDate> {
+    (1970i32..2100, 1u32..=12, 1u32..=28).prop_map(|(year, month, day)| NaiveDate::from_ymd_opt(year, month, day).unwrap())
+}
+
+fn time() -> impl Strategy<Value = NaiveTime> {
+    (0u32..24, 0u32..60).prop_map(|(hour, minute)| NaiveTime::from_hms_opt(hour, minute, 0).unwrap())
+}
+
+/// Words and values that occur in the period cells, in any order.
+fn period_like() -> impl Strategy<Value = String> {
+    let token = prop_oneof![
+        Just("ab".to_string()),
+        Just("bis".to_string()),
+        Just("seit".to_string()),
+        Just("vom".to_string()),
+        Just("auf Weiteres".to_string()),
+        Just("Uhr".to_string()),
+        Just(",".to_string()),
+        date().prop_map(|date| date.format("%d.%m.%Y").to_string()),
+        date().prop_map(|date| date.format("%d.%m.%y").to_string()),
+        time().prop_map(|time| time.format("%H:%M").to_string()),
+        "[a-zäöü]{1,8}",
+    ];
+    prop::collection::vec(token, 0..8).prop_map(|tokens| tokens.join(" "))
+}
+
+proptest! {
+    #[test]
+    fn never_panics_on_arbitrary_text(text in "\\PC*") {
+        let period = parse_period(&text);
+        let _ = (period.start_time(), period.end_time());
+    }
+
+    #[test]
+    fn never_panics_on_period_like_text(text in period_like()) {
+        let period = parse_period(&text);
+        let _ = (period.start_time(), period.end_time());
+    }
+
+    #[test]
+    fn whitespace_does_not_matter(text in period_like(), padding in "[ \n\t]{1,4}") {
+        let padded = text.split(' ').collect::<Vec<_>>().join(&padding);
+        prop_assert_eq!(parse_period(&padded), parse_period(&text));
+    }
+
+    #[test]
+    fn date_ranges(start in date(), days in 0i64..400) {
+        let end = start + Duration::days(days);
+        let period = parse_period(&format!("{} bis {}", start.format("%d.%m.%Y"), end.format("%d.%m.%Y")));
+        prop_assert_eq!(&period, &Period::Range { start: Moment::Date(start), end: Moment::Date(end) });
+        prop_assert!(period.start_time().unwrap() < period.end_time().unwrap());
+    }
+
+    #[test]
+    fn start_with_time_is_berlin_local(prefix in prop_oneof![Just("ab"), Just("seit")], date in date(), time in time()) {
+        let period = parse_period(&format!("{} {} {} Uhr", prefix, date.format("%d.%m.%Y"), time.format("%H:%M")));
+        let start = berlin_to_utc(date.and_time(time));
+        prop_assert_eq!(period, Period::From { start: Moment::DateTime(start) });
+    }
+
+    #[test]
+    fn end_time_only_ends_after_the_start(date in date(), from in time(), to in time()) {
+        let period = parse_period(&format!("{} {} bis {} Uhr", date.format("%d.%m.%Y"), from.format("%H:%M"), to.format("%H:%M")));
+        let start = berlin_to_utc(date.and_time(from));
+        // The start date, or the next day for an overnight window.
+        let end = match berlin_to_utc(date.and_time(to)) {
+            end if end < start => berlin_to_utc((date + Duration::days(1)).and_time(to)),
+            end => end,
+        };
+        prop_assert_eq!(&period, &Period::Range { start: Moment::DateTime(start), end: Moment::DateTime(end) });
+        let (start, end) = (period.start_time().unwrap(), period.end_time().unwrap());
+        prop_assert!(end >= start && end - start <= Duration::hours(25), "{} to {}", start, end);
+        // Equal only when the times are, or the start is moved out of the
+        // spring gap onto the end.
+        if from != to && berlin_to_utc(date.and_time(from)) != berlin_to_utc(date.and_time(to)) {
+            prop_assert!(end > start, "{} to {}", start, end);
+        }
+    }
+
+    #[test]
+    fn overnight_window(date in date()) {
+        let period = parse_period(&format!("{} 22:00 bis 04:00 Uhr", date.format("%d.%m.%Y")));
+        let next_day = date + Duration::days(1);
+        prop_assert_eq!(period.end_time().unwrap(), berlin_to_utc(next_day.and_hms_opt(4, 0, 0).unwrap()));
+        prop_assert!(period.end_time().unwrap() > period.start_time().unwrap());
+    }
+
+    #[test]
+    fn until_further_notice(start in date()) {
+        let period = parse_period(&format!("{} bis auf Weiteres", start.format("%d.%m.%Y")));
+        prop_assert_eq!(period, Period::UntilFurtherNotice { start: Some(Moment::Date(start)) });
+    }
+
+    #[test]
+    fn serde_round_trip(text in period_like()) {
+        let period = parse_period(&text);
+        let json = serde_json::to_string(&period).unwrap();
+        prop_assert_eq!(serde_json::from_str::<Period>(&json).unwrap(), period);
+    }
+
+    #[test]
+    fn berlin_to_utc_keeps_the_wall_clock(date in date(), time in time()) {
+        let local = date.and_time(time);
+        let back = berlin_to_utc(local).with_timezone(&Berlin).naive_local();
+        // Equal, or moved forward out of the spring gap.
+        prop_assert!(back == local || back == local + Duration::hours(1), "{} became {}", local, back);
+        prop_assert!(Berlin.from_local_datetime(&back).earliest().is_some());
+    }
+}
+
+#[test]
+fn spring_gap_start_does_not_invert_the_range() {
+    let period = parse_period("31.03.2024 02:30 bis 03:00 Uhr");
+    assert!(period.end_time().unwrap() >= period.start_time().unwrap(), "{:?}", period);
+}
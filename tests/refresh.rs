@@ -0,0 +1,101 @@
+//! Drives `refresh` through an `AppState` built around a `ScriptedSource`
+//! and checks what the API makes of each step.
+
+mod common;
+
+use schwebebahndisruption::refresh::refresh;
+use schwebebahndisruption::source::{FileSource, FixtureSource, ScriptedSource, Step};
+
+use common::{fixture, fixtures, get, pages, state};
+
+#[actix_web::test]
+async fn success_then_failure_then_structure_change() {
+    let state = state(ScriptedSource::new(vec![
+        Step::Page { status: 200, body: fixture("full_closure.html") },
+        Step::Fail("connection refused".to_string()),
+        Step::Page { status: 200, body: "<html><body><p>Wartungsarbeiten</p></body></html>".to_string() },
+    ]));
+
+    refresh(&state).await;
+    let (code, health) = get(&state, "/health").await;
+    assert_eq!(code, 200);
+    assert_eq!(health["consecutive_failures"], 0);
+    assert_eq!(health["parser_broken"], false);
+    let (_, feed) = get(&state, "/changes").await;
+    let added: Vec<&str> = feed["changes"].as_array().unwrap().iter().map(|change| change["key"].as_str().unwrap()).collect();
+    assert_eq!(added, ["ti-5001", "ti-5002"]);
+    assert!(feed["changes"].as_array().unwrap().iter().all(|change| change["kind"] == "added"));
+    assert_eq!(feed["cursor"], 2);
+
+    // An unreachable upstream is a failure, but says nothing about the parser.
+    refresh(&state).await;
+    let (code, health) = get(&state, "/health").await;
+    assert_eq!(code, 200);
+    assert_eq!(health["consecutive_failures"], 1);
+    assert_eq!(health["last_error"], "source failed: connection refused");
+    assert_eq!(health["parser_broken"], false);
+
+    refresh(&state).await;
+    let (code, health) = get(&state, "/health").await;
+    assert_eq!(code, 503);
+    assert_eq!(health["consecutive_failures"], 2);
+    assert_eq!(health["parser_broken"], true);
+    let (_, status) = get(&state, "/v2/status").await;
+    assert_eq!(status["data_available"], false);
+    assert_eq!(status["line"]["state"], "unknown");
+
+    // Failures don't touch the tracked disruptions.
+    let (_, feed) = get(&state, "/changes?since=2").await;
+    assert_eq!(feed["changes"].as_array().unwrap().len(), 0);
+    assert_eq!(feed["cursor"], 2);
+    assert_eq!(feed["has_more"], false);
+}
+
+#[actix_web::test]
+async fn disruptions_leaving_the_page_are_removed() {
+    let state = state(pages(&["full_closure.html", "only_elevators.html"]));
+
+    refresh(&state).await;
+    refresh(&state).await;
+    let (_, feed) = get(&state, "/changes?since=2").await;
+    let changes: Vec<(&str, &str)> = feed["changes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|change| (change["kind"].as_str().unwrap(), change["key"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        changes,
+        [("added", "ti-4711"), ("added", "ti-4712"), ("added", "ti-4713"), ("removed", "ti-5001"), ("removed", "ti-5002")]
+    );
+    let (_, status) = get(&state, "/status").await;
+    assert_eq!(status["schwebebahn"], serde_json::json!(["Keine aktuellen Störungen"]));
+}
+
+#[actix_web::test]
+async fn an_empty_page_after_a_busy_one_is_a_structure_change() {
+    let state = state(pages(&["full_closure.html", "no_disruptions.html"]));
+
+    refresh(&state).await;
+    refresh(&state).await;
+    let (code, health) = get(&state, "/health").await;
+    assert_eq!(code, 503);
+    assert_eq!(health["parser_broken"], true);
+    let (_, feed) = get(&state, "/changes?since=2").await;
+    assert_eq!(feed["changes"].as_array().unwrap().len(), 0);
+}
+
+#[actix_web::test]
+async fn fixture_and_file_sources_serve_the_same_page() {
+    let path = fixtures().join("only_elevators.html");
+    let from_fixture = state(FixtureSource::new(fixture("only_elevators.html")));
+    let from_file = state(FileSource::open(&path).unwrap());
+
+    for state in [&from_fixture, &from_file] {
+        refresh(state).await;
+        let (_, status) = get(state, "/v2/status").await;
+        assert_eq!(status["data_available"], true);
+        assert_eq!(status["schwebebahn_disruption_count"], 0);
+        assert_eq!(status["elevator_outage_count"], 3);
+    }
+}
@@ -0,0 +1,51 @@
+use schwebebahndisruption::segment::{parse_segment, Direction, Segment};
+use schwebebahndisruption::stations;
+
+fn segment(from: &str, to: &str, direction: Option<Direction>) -> Option<Segment> {
+    Some(Segment { from: from.to_string(), to: to.to_string(), direction })
+}
+
+#[test]
+fn locations() {
+    for (location, expected) in [
+        ("zwischen Ohligsmühle und Kluse", segment("ohligsmuehle", "kluse", None)),
+        ("zwischen Kluse und Ohligsmühle", segment("ohligsmuehle", "kluse", None)),
+        ("Zwischen Zoo / Stadion und Sonnborner Str.", segment("sonnborner-strasse", "zoo-stadion", None)),
+        ("Gesamtstrecke", segment("vohwinkel", "oberbarmen", None)),
+        ("Gesamtstrecke zwischen Vohwinkel und Oberbarmen", segment("vohwinkel", "oberbarmen", None)),
+        ("Haltestelle Landgericht", segment("landgericht", "landgericht", None)),
+        ("Haltestelle Wertherbrücke entfällt", segment("werther-bruecke", "werther-bruecke", None)),
+        ("Hofaue", None),
+        ("", None),
+    ] {
+        assert_eq!(parse_segment(location), expected, "{:?}", location);
+    }
+}
+
+#[test]
+fn directions() {
+    for (location, expected) in [
+        (
+            "zwischen Hauptbahnhof und Adlerbrücke in Fahrtrichtung Oberbarmen",
+            segment("hauptbahnhof", "adlerbruecke", Some(Direction::TowardsOberbarmen)),
+        ),
+        ("zwischen Hauptbahnhof und Adlerbrücke Richtung Vohwinkel", segment("hauptbahnhof", "adlerbruecke", Some(Direction::TowardsVohwinkel))),
+        // Named after a station beyond the segment rather than the terminus.
+        ("zwischen Bruch und Westende, Richtung Alter Markt", segment("bruch", "westende", Some(Direction::TowardsOberbarmen))),
+        ("Haltestelle Kluse, Richtung Vohwinkel", segment("kluse", "kluse", Some(Direction::TowardsVohwinkel))),
+        // A direction inside the segment says nothing.
+        ("zwischen Bruch und Westende Richtung Zoo", segment("bruch", "westende", None)),
+        ("Fahrtrichtung Oberbarmen", None),
+    ] {
+        assert_eq!(parse_segment(location), expected, "{:?}", location);
+    }
+}
+
+#[test]
+fn stations_are_inclusive_and_in_line_order() {
+    let segment = parse_segment("zwischen Kluse und Ohligsmühle").unwrap();
+    let ids: Vec<&str> = segment.stations().map(|station| station.id).collect();
+    assert_eq!(ids, ["ohligsmuehle", "hauptbahnhof", "kluse"]);
+    assert!(segment.contains(stations::by_id("hauptbahnhof").unwrap()));
+    assert!(!segment.contains(stations::by_id("landgericht").unwrap()));
+}
@@ -0,0 +1,64 @@
+//! `/status/stream` resuming from a `Last-Event-ID`.
+
+mod common;
+
+use actix_web::body::MessageBody;
+use actix_web::{test, web, App};
+use std::pin::pin;
+
+use schwebebahndisruption::refresh::refresh;
+use schwebebahndisruption::routes;
+
+use common::{pages, state};
+
+/// One server-sent event: its name, id and data.
+type Event = (String, i64, serde_json::Value);
+
+fn parse_event(block: &str) -> Event {
+    let (mut name, mut id, mut data) = (String::new(), 0, serde_json::Value::Null);
+    for line in block.lines() {
+        if let Some(value) = line.strip_prefix("event: ") {
+            name = value.to_string();
+        } else if let Some(value) = line.strip_prefix("id: ") {
+            id = value.parse().unwrap();
+        } else if let Some(value) = line.strip_prefix("data: ") {
+            data = serde_json::from_str(value).unwrap();
+        }
+    }
+    (name, id, data)
+}
+
+#[actix_web::test]
+async fn resumes_after_last_event_id() {
+    // Two added, then three added and two removed.
+    let state = state(pages(&["full_closure.html", "only_elevators.html"]));
+    refresh(&state).await;
+    refresh(&state).await;
+
+    let app = test::init_service(App::new().app_data(web::Data::new(state)).configure(routes)).await;
+    let request = test::TestRequest::get().uri("/status/stream").insert_header(("Last-Event-ID", "3")).to_request();
+    let response = test::call_service(&app, request).await;
+    assert_eq!(response.headers().get("Content-Type").unwrap(), "text/event-stream");
+
+    // The stream stays open, so read it up to the first `status` event.
+    let mut body = pin!(response.into_body());
+    let mut text = String::new();
+    let mut events: Vec<Event> = Vec::new();
+    while !events.iter().any(|(name, _, _)| name == "status") {
+        let chunk = std::future::poll_fn(|cx| body.as_mut().poll_next(cx)).await.unwrap().unwrap();
+        text.push_str(std::str::from_utf8(&chunk).unwrap());
+        while let Some(end) = text.find("\n\n") {
+            let block: String = text.drain(..end + 2).collect();
+            if !block.starts_with(':') {
+                events.push(parse_event(&block));
+            }
+        }
+    }
+
+    let ids: Vec<(&str, i64)> = events.iter().map(|(name, id, _)| (name.as_str(), *id)).collect();
+    assert_eq!(ids, [("change", 4), ("change", 5), ("change", 6), ("change", 7), ("status", 7)]);
+    for (_, id, data) in &events[..4] {
+        assert_eq!(data["cursor"], *id);
+    }
+    assert_eq!(events[4].2["schwebebahn"], serde_json::json!(["Keine aktuellen Störungen"]));
+}
@@ -0,0 +1,44 @@
+use schwebebahndisruption::stations::{self, STATIONS};
+
+#[test]
+fn registry_is_in_line_order_with_unique_ids() {
+    assert_eq!(STATIONS.first().unwrap().name, "Vohwinkel");
+    assert_eq!(STATIONS.last().unwrap().name, "Oberbarmen");
+    for (index, station) in STATIONS.iter().enumerate() {
+        assert_eq!(station.order as usize, index + 1, "{}", station.id);
+        assert_eq!(stations::by_id(station.id), Some(station));
+        // Every id must resolve to its own station, never to an earlier one.
+        assert_eq!(stations::lookup(station.id), Some(station));
+        for name in station.names() {
+            assert_eq!(stations::lookup(name), Some(station), "{:?}", name);
+        }
+    }
+}
+
+#[test]
+fn lookup_folds_spelling() {
+    for (name, id) in [
+        ("Sonnborner Str.", "sonnborner-strasse"),
+        ("sonnborner strasse", "sonnborner-strasse"),
+        ("Zoo / Stadion", "zoo-stadion"),
+        ("  Ohligsmuehle ", "ohligsmuehle"),
+        ("Wertherbrücke", "werther-bruecke"),
+        ("Wuppertal Hbf", "hauptbahnhof"),
+        ("ROBERT DAUM PLATZ", "robert-daum-platz"),
+    ] {
+        assert_eq!(stations::lookup(name).map(|station| station.id), Some(id), "{:?}", name);
+    }
+    assert_eq!(stations::lookup("Ronsdorf"), None);
+    assert_eq!(stations::lookup(""), None);
+}
+
+#[test]
+fn mentions_match_whole_words() {
+    let bruch = stations::by_id("bruch").unwrap();
+    assert!(bruch.is_mentioned_in("zwischen Vohwinkel und Bruch"));
+    assert!(!bruch.is_mentioned_in("Rohrbruch an der Loher Brücke"));
+
+    let werther_bruecke = stations::by_id("werther-bruecke").unwrap();
+    assert!(werther_bruecke.is_mentioned_in("Haltestelle Wertherbrücke entfällt"));
+    assert!(werther_bruecke.is_mentioned_in("zwischen Alter Markt und Werther Brücke."));
+}
@@ -0,0 +1,208 @@
+//! `Store::track` against an in-memory database: how rows are matched
+//! across scrapes, which changes that records and how `/changes` pages
+//! through them.
+
+mod common;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+
+use schwebebahndisruption::changes::{Change, ChangeKind};
+use schwebebahndisruption::lifecycle::Disruption;
+use schwebebahndisruption::period::Period;
+use schwebebahndisruption::refresh::refresh;
+use schwebebahndisruption::segment::parse_segment;
+use schwebebahndisruption::store::Store;
+use schwebebahndisruption::{ElevatorStatus, SchwebebahnDisruption};
+
+use common::{get, pages, state};
+
+fn store() -> Store {
+    Store::open(":memory:").unwrap()
+}
+
+/// The time of the `n`th scrape.
+fn scrape(n: i64) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap() + Duration::minutes(15 * n)
+}
+
+fn elevator(id: &str, station: &str, info: &str) -> Disruption {
+    Disruption::Elevator(ElevatorStatus {
+        id: id.to_string(),
+        station: station.to_string(),
+        station_id: None,
+        event: "Aufzugsstörung".to_string(),
+        start_time: None,
+        end_time: None,
+        period: Period::Unknown,
+        raw_period: String::new(),
+        location: "Aufzug zum Bahnsteig".to_string(),
+        info: info.to_string(),
+    })
+}
+
+fn subway(id: &str, location: &str) -> Disruption {
+    Disruption::Subway(SchwebebahnDisruption {
+        id: id.to_string(),
+        event: "Bauarbeiten".to_string(),
+        location: location.to_string(),
+        segment: parse_segment(location),
+        start_time: None,
+        end_time: None,
+        period: Period::Unknown,
+        raw_period: String::new(),
+        info: String::new(),
+    })
+}
+
+fn kinds(changes: &[Change]) -> Vec<(ChangeKind, &str)> {
+    changes.iter().map(|change| (change.kind, change.key.as_str())).collect()
+}
+
+#[test]
+fn rows_are_matched_by_id() {
+    let store = store();
+    let rows = [elevator("ti-1", "Kluse", ""), subway("ti-2", "zwischen Ohligsmühle und Kluse")];
+
+    let changes = store.track(scrape(0), &rows).unwrap();
+    assert_eq!(kinds(&changes), [(ChangeKind::Added, "ti-1"), (ChangeKind::Added, "ti-2")]);
+    assert!(changes.iter().all(|change| change.at == scrape(0) && change.previous.is_none()));
+
+    // Same ids, even in another order: nothing to report.
+    assert!(store.track(scrape(1), &[rows[1].clone(), rows[0].clone()]).unwrap().is_empty());
+
+    let active = store.active_disruptions().unwrap();
+    assert_eq!(active.len(), 2);
+    assert!(active.iter().all(|tracked| tracked.first_seen == scrape(0) && tracked.last_seen == scrape(1)));
+}
+
+#[test]
+fn a_row_listed_twice_is_tracked_once() {
+    let store = store();
+    let row = elevator("ti-1", "Kluse", "");
+
+    let changes = store.track(scrape(0), &[row.clone(), row]).unwrap();
+    assert_eq!(kinds(&changes), [(ChangeKind::Added, "ti-1")]);
+    assert_eq!(store.active_disruptions().unwrap().len(), 1);
+}
+
+#[test]
+fn rows_without_id_are_matched_by_content() {
+    let store = store();
+    let row = elevator("", "Kluse", "Techniker ist informiert");
+
+    let changes = store.track(scrape(0), std::slice::from_ref(&row)).unwrap();
+    let key = row.key();
+    assert!(key.starts_with("sha256:"), "{}", key);
+    assert_eq!(kinds(&changes), [(ChangeKind::Added, key.as_str())]);
+    assert!(store.track(scrape(1), std::slice::from_ref(&row)).unwrap().is_empty());
+
+    // The info text isn't part of the hash, so an update keeps the entry.
+    let updated = elevator("", "Kluse", "Ersatzteil ist bestellt");
+    assert_eq!(updated.key(), key);
+    let changes = store.track(scrape(2), std::slice::from_ref(&updated)).unwrap();
+    assert_eq!(kinds(&changes), [(ChangeKind::Modified, key.as_str())]);
+
+    // Another station is another disruption.
+    let moved = elevator("", "Landgericht", "Ersatzteil ist bestellt");
+    let changes = store.track(scrape(3), std::slice::from_ref(&moved)).unwrap();
+    assert_eq!(kinds(&changes), [(ChangeKind::Added, moved.key().as_str()), (ChangeKind::Removed, key.as_str())]);
+}
+
+#[test]
+fn missing_rows_are_resolved() {
+    let store = store();
+    let (kept, gone) = (elevator("ti-1", "Kluse", ""), elevator("ti-2", "Oberbarmen", ""));
+    store.track(scrape(0), &[kept.clone(), gone.clone()]).unwrap();
+
+    let changes = store.track(scrape(1), std::slice::from_ref(&kept)).unwrap();
+    assert_eq!(kinds(&changes), [(ChangeKind::Removed, "ti-2")]);
+    // Removals carry the last known state.
+    assert_eq!(changes[0].disruption, gone);
+
+    let active = store.active_disruptions().unwrap();
+    assert_eq!(active.iter().map(|tracked| tracked.key.as_str()).collect::<Vec<_>>(), ["ti-1"]);
+    let resolved = store.resolved_since(scrape(1)).unwrap();
+    assert_eq!(resolved.len(), 1);
+    assert_eq!(resolved[0].key, "ti-2");
+    assert_eq!(resolved[0].resolved_at, Some(scrape(1)));
+    assert_eq!(resolved[0].last_seen, scrape(0));
+
+    // Coming back starts a new entry rather than reopening the old one.
+    let changes = store.track(scrape(2), &[kept, gone]).unwrap();
+    assert_eq!(kinds(&changes), [(ChangeKind::Added, "ti-2")]);
+    let returned = store.active_disruptions().unwrap().into_iter().find(|tracked| tracked.key == "ti-2").unwrap();
+    assert_eq!(returned.first_seen, scrape(2));
+    assert_eq!(store.resolved_since(scrape(0)).unwrap().len(), 1);
+
+    // An empty scrape resolves everything.
+    let changes = store.track(scrape(3), &[]).unwrap();
+    assert_eq!(kinds(&changes), [(ChangeKind::Removed, "ti-1"), (ChangeKind::Removed, "ti-2")]);
+    assert!(store.active_disruptions().unwrap().is_empty());
+}
+
+#[test]
+fn modified_rows_keep_the_previous_state() {
+    let store = store();
+    let before = subway("ti-1", "zwischen Ohligsmühle und Kluse");
+    store.track(scrape(0), std::slice::from_ref(&before)).unwrap();
+
+    let after = subway("ti-1", "zwischen Ohligsmühle und Hauptbahnhof");
+    let changes = store.track(scrape(1), std::slice::from_ref(&after)).unwrap();
+    assert_eq!(kinds(&changes), [(ChangeKind::Modified, "ti-1")]);
+    assert_eq!(changes[0].disruption, after);
+    assert_eq!(changes[0].previous, Some(before));
+
+    let active = store.active_disruptions().unwrap();
+    assert_eq!(active.len(), 1);
+    assert_eq!(active[0].disruption, after);
+    assert_eq!(active[0].first_seen, scrape(0));
+}
+
+#[test]
+fn cursors_increase_across_scrapes() {
+    let store = store();
+    let (a, b) = (elevator("ti-1", "Kluse", ""), elevator("ti-2", "Oberbarmen", ""));
+    let mut cursors = Vec::new();
+    for (n, rows) in [vec![a.clone()], vec![a.clone(), b.clone()], vec![b], vec![], vec![a]].into_iter().enumerate() {
+        let changes = store.track(scrape(n as i64), &rows).unwrap();
+        cursors.extend(changes.iter().map(|change| change.cursor));
+        assert_eq!(store.latest_cursor().unwrap(), cursors.last().copied().unwrap_or(0));
+    }
+    assert_eq!(cursors, (1..=5).collect::<Vec<i64>>());
+
+    // The feed reads back what `track` returned, in pages.
+    let all: Vec<i64> = store.changes_since(0, u32::MAX).unwrap().iter().map(|change| change.cursor).collect();
+    assert_eq!(all, cursors);
+    let page: Vec<i64> = store.changes_since(2, 2).unwrap().iter().map(|change| change.cursor).collect();
+    assert_eq!(page, [3, 4]);
+    assert!(store.changes_since(5, 10).unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn changes_pages_until_has_more_is_false() {
+    // Two added, then three added and two removed.
+    let state = state(pages(&["full_closure.html", "only_elevators.html"]));
+    refresh(&state).await;
+    refresh(&state).await;
+
+    let mut since = 0;
+    let mut pages = Vec::new();
+    loop {
+        let uri = format!("/changes?since={}&limit=3", since);
+        let (_, feed) = get(&state, &uri).await;
+        let cursors: Vec<i64> = feed["changes"].as_array().unwrap().iter().map(|change| change["cursor"].as_i64().unwrap()).collect();
+        assert_eq!(feed["cursor"].as_i64().unwrap(), cursors.last().copied().unwrap_or(since));
+        pages.push(cursors);
+        since = feed["cursor"].as_i64().unwrap();
+        if feed["has_more"] == false {
+            break;
+        }
+    }
+    assert_eq!(pages, [vec![1, 2, 3], vec![4, 5, 6], vec![7]]);
+
+    // Caught up: an empty page that keeps the cursor.
+    let (_, feed) = get(&state, "/changes?since=7").await;
+    assert_eq!(feed["changes"].as_array().unwrap().len(), 0);
+    assert_eq!(feed["cursor"], 7);
+    assert_eq!(feed["has_more"], false);
+}
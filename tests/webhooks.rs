@@ -0,0 +1,178 @@
+//! Delivers changes to a local receiver and checks signing, retries, the
+//! dead-letter log and catching up after the dispatcher fell behind.
+
+mod common;
+
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use schwebebahndisruption::refresh::refresh;
+use schwebebahndisruption::source::{DisruptionSource, FixtureSource};
+use schwebebahndisruption::webhooks::{dispatch, Webhook};
+use schwebebahndisruption::AppState;
+
+use common::{fixture, pages, state_in};
+
+const SECRET: &str = "test-secret";
+
+/// A fresh database file, as the dead letters are checked from outside.
+fn database(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("schwebebahn-webhooks-{}-{}.db", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn state(database: &Path, source: impl DisruptionSource + 'static) -> Arc<AppState> {
+    state_in(&database.to_string_lossy(), source)
+}
+
+/// What the receiver got: the signature header and the body.
+type Received = Arc<Mutex<Vec<(String, String)>>>;
+
+/// Answers with the given statuses in turn and then 200, recording every request.
+async fn receiver(mut statuses: Vec<u16>) -> (String, Received) {
+    let received = Received::default();
+    let responses = Arc::new(Mutex::new(statuses.drain(..).collect::<VecDeque<u16>>()));
+    let (log, queue) = (Arc::clone(&received), Arc::clone(&responses));
+    let server = HttpServer::new(move || {
+        let (log, queue) = (Arc::clone(&log), Arc::clone(&queue));
+        App::new().default_service(web::to(move |req: HttpRequest, body: String| {
+            let (log, queue) = (Arc::clone(&log), Arc::clone(&queue));
+            async move {
+                let signature = req.headers().get("X-Signature-256").and_then(|value| value.to_str().ok()).unwrap_or_default();
+                log.lock().unwrap().push((signature.to_string(), body));
+                let status = queue.lock().unwrap().pop_front().unwrap_or(200);
+                HttpResponse::build(actix_web::http::StatusCode::from_u16(status).unwrap()).finish()
+            }
+        }))
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+    let url = format!("http://{}/hook", server.addrs()[0]);
+    actix_web::rt::spawn(server.run());
+    (url, received)
+}
+
+fn webhook(url: &str, max_attempts: u32) -> Webhook {
+    Webhook {
+        url: url.to_string(),
+        secret: SECRET.to_string(),
+        stations: Vec::new(),
+        events: Vec::new(),
+        transportation: Vec::new(),
+        max_attempts,
+        initial_backoff_ms: 10,
+    }
+}
+
+/// Waits until `received` holds at least `count` requests.
+async fn wait_for(received: &Received, count: usize) {
+    for _ in 0..200 {
+        if received.lock().unwrap().len() >= count {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("expected {} requests, got {}", count, received.lock().unwrap().len());
+}
+
+fn dead_letters(database: &Path) -> Vec<(String, u32, String)> {
+    let conn = rusqlite::Connection::open(database).unwrap();
+    let mut statement = conn.prepare("SELECT url, attempts, last_error FROM webhook_dead_letters ORDER BY id").unwrap();
+    let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))).unwrap();
+    rows.collect::<Result<_, _>>().unwrap()
+}
+
+fn expected_signature(body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(SECRET.as_bytes()).unwrap();
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Starts the dispatcher and gives it a moment to subscribe.
+async fn start_dispatch(state: &Arc<AppState>, webhooks: Vec<Webhook>) {
+    tokio::spawn(dispatch(Arc::clone(state), webhooks));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+}
+
+#[actix_web::test]
+async fn signed_and_retried_after_a_server_error() {
+    let (url, received) = receiver(vec![500]).await;
+    let database = database("retry");
+    let state = state(&database, FixtureSource::new(fixture("full_closure.html")));
+    start_dispatch(&state, vec![webhook(&url, 3)]).await;
+
+    refresh(&state).await;
+    wait_for(&received, 2).await;
+    // Give a wrongly scheduled third attempt the chance to show up.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let received = received.lock().unwrap().clone();
+    assert_eq!(received.len(), 2);
+    assert_eq!(received[0], received[1]);
+    let (signature, body) = &received[0];
+    assert_eq!(*signature, expected_signature(body));
+    let payload: serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(payload["cursor"], 2);
+    assert_eq!(payload["changes"].as_array().unwrap().len(), 2);
+    assert!(dead_letters(&database).is_empty());
+    let _ = fs::remove_file(&database);
+}
+
+#[actix_web::test]
+async fn dead_letter_after_max_attempts() {
+    let (url, received) = receiver(vec![500; 10]).await;
+    let database = database("dead-letter");
+    let state = state(&database, FixtureSource::new(fixture("full_closure.html")));
+    start_dispatch(&state, vec![webhook(&url, 3)]).await;
+
+    refresh(&state).await;
+    wait_for(&received, 3).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(received.lock().unwrap().len(), 3);
+    assert_eq!(dead_letters(&database), [(url, 3, "HTTP 500 Internal Server Error".to_string())]);
+    let _ = fs::remove_file(&database);
+}
+
+#[actix_web::test]
+async fn catches_up_from_the_store_after_falling_behind() {
+    let (url, received) = receiver(Vec::new()).await;
+    let database = database("lagged");
+    // Every page differs from the one before, so every scrape records changes.
+    let state = state(&database, pages(&["full_closure.html", "only_elevators.html"].repeat(10)));
+    start_dispatch(&state, vec![webhook(&url, 1)]).await;
+
+    // More updates than the channel holds, before the dispatcher gets to run.
+    for _ in 0..20 {
+        refresh(&state).await;
+    }
+    let latest = schwebebahndisruption::store::Store::open(&database.to_string_lossy()).unwrap().latest_cursor().unwrap();
+    let mut delivered = Vec::new();
+    for _ in 0..200 {
+        delivered = received
+            .lock()
+            .unwrap()
+            .iter()
+            .flat_map(|(_, body)| {
+                let payload: serde_json::Value = serde_json::from_str(body).unwrap();
+                payload["changes"].as_array().unwrap().iter().map(|change| change["cursor"].as_i64().unwrap()).collect::<Vec<_>>()
+            })
+            .collect();
+        if delivered.len() as i64 >= latest {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    delivered.sort();
+    assert_eq!(delivered, (1..=latest).collect::<Vec<_>>());
+    let _ = fs::remove_file(&database);
+}
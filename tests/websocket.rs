@@ -0,0 +1,112 @@
+//! `/ws` over a real connection: subscription handling and which changes
+//! reach a `station:<name>` subscriber.
+
+mod common;
+
+use actix_web::{web, App, HttpServer};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use schwebebahndisruption::refresh::refresh;
+use schwebebahndisruption::source::FixtureSource;
+use schwebebahndisruption::{routes, AppState};
+
+use common::{fixture, pages, state};
+
+type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Serves the API around `state` on a free port and connects to `/ws`.
+async fn connect(state: &Arc<AppState>) -> Client {
+    let state = Arc::clone(state);
+    let server = HttpServer::new(move || App::new().app_data(web::Data::new(Arc::clone(&state))).configure(routes))
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+    let url = format!("ws://{}/ws", server.addrs()[0]);
+    actix_web::rt::spawn(server.run());
+    tokio_tungstenite::connect_async(url).await.unwrap().0
+}
+
+async fn send(client: &mut Client, message: Value) {
+    client.send(Message::Text(message.to_string())).await.unwrap();
+}
+
+/// The next text message, skipping control frames.
+async fn receive(client: &mut Client) -> Value {
+    loop {
+        let message = tokio::time::timeout(Duration::from_secs(5), client.next()).await.expect("no message").unwrap().unwrap();
+        if let Message::Text(text) = message {
+            return serde_json::from_str(&text).unwrap();
+        }
+    }
+}
+
+#[actix_web::test]
+async fn acks_carry_the_full_topic_set() {
+    let state = state(FixtureSource::new(fixture("no_disruptions.html")));
+    let mut client = connect(&state).await;
+
+    send(&mut client, json!({"type": "subscribe", "id": "1", "topics": ["schwebebahn", "station:kluse"]})).await;
+    assert_eq!(
+        receive(&mut client).await,
+        json!({"type": "ack", "action": "subscribe", "id": "1", "topics": ["schwebebahn", "station:kluse"]})
+    );
+
+    send(&mut client, json!({"type": "subscribe", "id": "2", "topics": ["elevators", "schwebebahn"]})).await;
+    assert_eq!(
+        receive(&mut client).await,
+        json!({"type": "ack", "action": "subscribe", "id": "2", "topics": ["elevators", "schwebebahn", "station:kluse"]})
+    );
+
+    send(&mut client, json!({"type": "unsubscribe", "id": "3", "topics": ["schwebebahn", "station:oberbarmen"]})).await;
+    assert_eq!(
+        receive(&mut client).await,
+        json!({"type": "ack", "action": "unsubscribe", "id": "3", "topics": ["elevators", "station:kluse"]})
+    );
+}
+
+#[actix_web::test]
+async fn unknown_topics_are_rejected() {
+    let state = state(FixtureSource::new(fixture("no_disruptions.html")));
+    let mut client = connect(&state).await;
+
+    send(&mut client, json!({"type": "subscribe", "id": "1", "topics": ["elevators", "trams"]})).await;
+    assert_eq!(receive(&mut client).await, json!({"type": "error", "id": "1", "message": "unknown topic \"trams\""}));
+    send(&mut client, json!({"type": "subscribe", "id": "2", "topics": ["station: "]})).await;
+    assert_eq!(receive(&mut client).await, json!({"type": "error", "id": "2", "message": "unknown topic \"station: \""}));
+
+    // A rejected request subscribes to none of its topics.
+    send(&mut client, json!({"type": "ping", "id": "3"})).await;
+    assert_eq!(receive(&mut client).await, json!({"type": "ack", "action": "ping", "id": "3", "topics": []}));
+}
+
+#[actix_web::test]
+async fn station_alias_matches_elevators_and_subway_segments() {
+    // An elevator at Hauptbahnhof, then a subway row covering the whole line.
+    let state = state(pages(&["station_names.html", "full_closure.html"]));
+    let mut client = connect(&state).await;
+
+    send(&mut client, json!({"type": "subscribe", "topics": ["station:Wuppertal Hbf"]})).await;
+    assert_eq!(receive(&mut client).await["type"], "ack");
+
+    refresh(&state).await;
+    refresh(&state).await;
+    let mut received = Vec::new();
+    for _ in 0..3 {
+        let message = receive(&mut client).await;
+        assert_eq!(message["type"], "change");
+        assert_eq!(message["topics"], json!(["station:Wuppertal Hbf"]));
+        let change = &message["change"];
+        received.push((change["kind"].as_str().unwrap().to_string(), change["key"].as_str().unwrap().to_string()));
+    }
+    let expected = [("added", "ti-8003"), ("added", "ti-5001"), ("removed", "ti-8003")];
+    assert_eq!(received, expected.map(|(kind, key)| (kind.to_string(), key.to_string())));
+    // Nothing else was sent before the answer to this.
+    send(&mut client, json!({"type": "ping"})).await;
+    assert_eq!(receive(&mut client).await["type"], "ack");
+}